@@ -2,15 +2,13 @@ use chrono::{Duration, Local, Utc};
 use elkato_client::{Client, ListOptions};
 use elkato_client::{Config, User};
 use elkato_common::data::Booking;
-use futures::{StreamExt, TryStreamExt};
+use futures::TryStreamExt;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
-    let client = Client::new(Config {
-        url: "https://www.elkato.de".parse()?,
-    })?;
+    let client = Client::new(Config::new("https://www.elkato.de".parse()?))?;
 
     let now = Local::now().with_timezone(&Utc);
 
@@ -28,7 +26,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 ..Default::default()
             },
         )
-        .boxed()
         .try_collect()
         .await?;
 
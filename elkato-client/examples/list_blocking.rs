@@ -0,0 +1,33 @@
+use chrono::{Duration, Local, Utc};
+use elkato_client::blocking::Client;
+use elkato_client::{Config, ListOptions, User};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let client = Client::new(Config::new("https://www.elkato.de".parse()?))?;
+
+    let now = Local::now().with_timezone(&Utc);
+
+    let mut count = 0;
+    for booking in client.list_bookings(
+        User {
+            club: "demo".into(),
+            username: "demo".into(),
+            password: Some("demo".into()),
+        },
+        ListOptions {
+            owner: Some("demo".into()),
+            start_from: Some(now.date() - Duration::days(7)),
+            end_to: Some(now.date() + Duration::days(7)),
+            ..Default::default()
+        },
+    ) {
+        println!("{:?}", booking?);
+        count += 1;
+    }
+
+    println!("Found {} bookings", count);
+
+    Ok(())
+}
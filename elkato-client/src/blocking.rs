@@ -0,0 +1,92 @@
+//! A blocking client, for use outside of an async runtime.
+//!
+//! Each client runs its own runtime, so it must not be used from within another one.
+
+use crate::client::ListOptions;
+use crate::config::{Config, User};
+use crate::error::{Error, Result};
+use crate::transport::BoxStream;
+use elkato_common::data::{Booking, BookingChanges, BookingDetails, NewBooking, Resource};
+use futures::StreamExt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+
+/// A blocking version of the [`Client`](crate::Client).
+#[derive(Clone, Debug)]
+pub struct Client {
+    client: crate::Client,
+    runtime: Arc<Mutex<Runtime>>,
+}
+
+/// Run a future to completion on the runtime.
+fn block_on<F: Future>(runtime: &Mutex<Runtime>, f: F) -> F::Output {
+    runtime
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .block_on(f)
+}
+
+impl Client {
+    pub fn new(config: Config) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .map_err(|err| Error::Transport(Box::new(err)))?;
+
+        Ok(Client {
+            client: crate::Client::new(config)?,
+            runtime: Arc::new(Mutex::new(runtime)),
+        })
+    }
+
+    /// List all bookings matching the options.
+    ///
+    /// Pages are fetched while iterating.
+    pub fn list_bookings(&self, user: User, options: ListOptions) -> Bookings {
+        Bookings {
+            stream: self.client.list_bookings(user, options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// List the resources (cars) of the club.
+    pub fn list_resources(&self, user: User) -> Result<Vec<Resource>> {
+        block_on(&self.runtime, self.client.list_resources(user))
+    }
+
+    /// Get all details of a single booking.
+    pub fn get_booking(&self, user: User, id: &str) -> Result<BookingDetails> {
+        block_on(&self.runtime, self.client.get_booking(user, id))
+    }
+
+    /// Create a new booking.
+    pub fn create_booking(&self, user: User, booking: NewBooking) -> Result<Booking> {
+        block_on(&self.runtime, self.client.create_booking(user, booking))
+    }
+
+    /// Change an existing booking.
+    pub fn update_booking(&self, user: User, id: &str, changes: BookingChanges) -> Result<()> {
+        block_on(&self.runtime, self.client.update_booking(user, id, changes))
+    }
+
+    /// Cancel a booking.
+    pub fn cancel_booking(&self, user: User, id: &str) -> Result<()> {
+        block_on(&self.runtime, self.client.cancel_booking(user, id))
+    }
+}
+
+/// An iterator over bookings, returned by [`Client::list_bookings`].
+pub struct Bookings {
+    stream: BoxStream<'static, Result<Booking>>,
+    runtime: Arc<Mutex<Runtime>>,
+}
+
+impl Iterator for Bookings {
+    type Item = Result<Booking>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(&self.runtime, self.stream.next())
+    }
+}
@@ -1,43 +1,102 @@
 use crate::config::{Config, User};
+use crate::error::{Error, Rejection, Result};
 use crate::parser;
-use anyhow::Context;
-use chrono::{Date, Datelike, Utc};
-use elkato_common::data::Booking;
-use futures::TryStream;
-use futures::{stream, TryStreamExt};
-use reqwest::header::{self, HeaderValue};
-use tokio::stream::StreamExt;
+use chrono::{Date, DateTime, Datelike, Duration, Timelike, Utc};
+use chrono_tz::Tz;
+use elkato_common::data::{
+    Availability, Booking, BookingChanges, BookingDetails, MemberId, NewBooking, Resource,
+    ResourceId,
+};
+use futures::{future, stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::Arc;
 use url::{ParseError, Url};
 
+pub use elkato_common::data::BookingState;
+
+#[cfg(feature = "reqwest")]
+use crate::transport::ReqwestTransport;
+use crate::transport::{BoxStream, Method, Request, Transport};
+
 #[derive(Clone, Debug)]
 pub struct Client {
     config: Config,
-    client: reqwest::Client,
-}
-
-#[derive(Clone, Debug)]
-pub enum BookingState {
-    Active,
-    Inactive,
-    All,
+    transport: Arc<dyn Transport>,
 }
 
-impl Default for BookingState {
-    fn default() -> Self {
-        BookingState::Active
-    }
-}
-
-#[derive(Clone, Debug, Default)]
+/// Options for listing bookings.
+///
+/// When serialized, dates are written as `YYYY-MM-DD`, so that the options can be used as query
+/// parameters.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ListOptions {
-    pub owner: Option<String>,
+    /// Only list bookings made for this member.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<MemberId>,
+    /// Only list bookings of this resource.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<ResourceId>,
+    /// Only list bookings made by this member.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub booker: Option<MemberId>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "date_option")]
     pub start_from: Option<Date<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "date_option")]
     pub start_to: Option<Date<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "date_option")]
     pub end_from: Option<Date<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "date_option")]
     pub end_to: Option<Date<Utc>>,
     pub state: BookingState,
 }
 
+/// Serialize optional dates as `YYYY-MM-DD`.
+mod date_option {
+    use chrono::{Date, NaiveDate, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y-%m-%d";
+
+    pub fn serialize<S>(date: &Option<Date<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => serializer.serialize_str(&date.format(FORMAT).to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Date<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => NaiveDate::parse_from_str(&s, FORMAT)
+                .map(|date| Some(Date::from_utc(date, Utc)))
+                .map_err(de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The position of a page in the search results.
+///
+/// A cursor is only valid for the options it was returned for.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    /// The offset of the first entry of the page.
+    pub offset: usize,
+    /// The total number of entries, as reported by the previous page.
+    ///
+    /// When set, the page must report the same total, otherwise the search results changed since
+    /// the previous page, and the cursor is no longer valid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+}
+
 fn date_filter_to_query(prefix: &str, date: Option<Date<Utc>>) -> Vec<(String, String)> {
     match date {
         Some(d) => vec![
@@ -50,129 +109,527 @@ fn date_filter_to_query(prefix: &str, date: Option<Date<Utc>>) -> Vec<(String, S
     }
 }
 
-impl Client {
-    pub fn new(config: Config) -> anyhow::Result<Self> {
-        let mut headers = header::HeaderMap::new();
+fn datetime_to_form(prefix: &str, date: &DateTime<Utc>, tz: Tz) -> Vec<(String, String)> {
+    let date = date.with_timezone(&tz);
+    vec![
+        (format!("{}_day", prefix), date.day().to_string()),
+        (format!("{}_month", prefix), date.month().to_string()),
+        (format!("{}_year", prefix), date.year().to_string()),
+        (format!("{}_hour", prefix), date.hour().to_string()),
+        (format!("{}_minute", prefix), date.minute().to_string()),
+    ]
+}
+
+/// Drop the seconds of a time, as Elkato only handles minutes.
+fn truncate_to_minute(date: DateTime<Utc>) -> DateTime<Utc> {
+    date - Duration::nanoseconds(i64::from(date.nanosecond()))
+        - Duration::seconds(i64::from(date.second()))
+}
+
+/// Check that a time range isn't empty, or reversed.
+fn check_range(range: &Range<DateTime<Utc>>) -> Result<()> {
+    if range.start < range.end {
+        Ok(())
+    } else {
+        Err(Error::InvalidOptions(format!(
+            "Empty time range: {} - {}",
+            range.start, range.end
+        )))
+    }
+}
 
-        headers.insert(
-            "Accept-Language",
-            HeaderValue::from_static("de-DE;de;q=0.5"),
-        );
+impl Client {
+    /// Create a new client, using `reqwest`.
+    #[cfg(feature = "reqwest")]
+    pub fn new(config: Config) -> Result<Self> {
+        Self::with_transport(config, ReqwestTransport::new()?)
+    }
 
-        let client = reqwest::ClientBuilder::new()
-            .default_headers(headers)
-            .build()
-            .context("Failed to create HTTP client")?;
+    /// Create a new client, sending requests through the provided transport.
+    pub fn with_transport<T>(config: Config, transport: T) -> Result<Self>
+    where
+        T: Transport + 'static,
+    {
+        if !config
+            .language
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(Error::InvalidOptions(format!(
+                "Invalid language: {}",
+                config.language
+            )));
+        }
 
-        Ok(Client { config, client })
+        Ok(Client {
+            config,
+            transport: Arc::new(transport),
+        })
     }
 
+    /// List all bookings matching the options.
+    ///
+    /// The stream doesn't borrow the client, so it can be moved into spawned tasks. After the
+    /// first page, up to [`Config::page_concurrency`] pages are fetched at the same time.
+    /// Bookings are returned in order either way.
     pub fn list_bookings(
         &self,
         user: User,
         options: ListOptions,
-    ) -> impl TryStream<Item = Result<Booking, anyhow::Error>> + '_ {
-        #[derive(Clone)]
-        struct ListState {
-            offset: Option<usize>,
-            client: reqwest::Client,
-            user: User,
-            url: Result<Url, ParseError>,
-            options: ListOptions,
-        }
+    ) -> BoxStream<'static, Result<Booking>> {
+        let search = match self.config.url.join("/buchung/search.php") {
+            Ok(url) => Search {
+                client: self.clone(),
+                url,
+                user,
+                options,
+                ctx: self.context(),
+            },
+            Err(err) => return Box::pin(stream::once(future::ready(Err(err.into())))),
+        };
+        let concurrency = self.config.page_concurrency.max(1);
+
+        let stream = stream::once(async move {
+            let first = search.page(0).await?;
 
-        let url = self.config.url.join("/buchung/search.php");
-        let client = self.client.clone();
+            // the offsets of all other pages are known after the first one
+            let (offsets, total) = match first.paging {
+                Some(p) if p.to < p.total => ((p.to..p.total).step_by(p.page_size()), p.total),
+                _ => ((0..0).step_by(1), 0),
+            };
 
-        let init = ListState {
-            offset: Some(0),
-            url,
-            client,
+            let rest = stream::iter(offsets)
+                .map(move |offset| {
+                    let search = search.clone();
+                    async move { search.next_page(offset, total).await }
+                })
+                .buffered(concurrency)
+                .map_ok(|page| stream::iter(page.bookings.into_iter().map(Ok)));
+
+            Result::Ok(stream::iter(first.bookings.into_iter().map(Ok)).chain(rest.try_flatten()))
+        })
+        .try_flatten();
+
+        Box::pin(stream)
+    }
+
+    /// Fetch a single page of bookings, starting at `cursor`, or at the first page.
+    ///
+    /// Returns the bookings, and the cursor of the next page, if there is one. Fails, if the search
+    /// results changed since the cursor was created.
+    pub async fn list_bookings_page(
+        &self,
+        user: User,
+        options: ListOptions,
+        cursor: Option<Cursor>,
+    ) -> Result<(Vec<Booking>, Option<Cursor>)> {
+        let search = Search {
+            client: self.clone(),
+            url: self.config.url.join("/buchung/search.php")?,
             user,
             options,
+            ctx: self.context(),
         };
 
-        stream::try_unfold(init, move |state| {
-            async move {
-                let next = state.clone();
-
-                match state.offset {
-                    // having no offset means, we finish up in the last iteration
-                    None => Result::<_, anyhow::Error>::Ok(None),
-                    // having an offset means we need to pull in more data
-                    Some(offset) => {
-                        let builder = state
-                            .client
-                            .get(state.url.clone()?)
-                            .basic_auth(state.user.username.clone(), state.user.password.clone())
-                            .query(&[
-                                ("club", state.user.club.clone()),
-                                ("search_pos", format!("{}", offset)),
-                                ("sel_room", "all".into()),
-                                ("sel_booker", "all".into()),
-                                (
-                                    "sel_owner",
-                                    state.options.owner.unwrap_or_else(|| "all".into()),
-                                ),
-                            ]);
-
-                        let builder = builder.query(match &state.options.state {
-                            BookingState::Active => &[("active", "on")][..],
-                            BookingState::Inactive => &[("inactive", "on")][..],
-                            BookingState::All => &[("active", "on"), ("inactive", "on")][..],
-                        });
-
-                        let builder = builder
-                            .query(&date_filter_to_query("s_from", state.options.start_from));
-                        let builder =
-                            builder.query(&date_filter_to_query("s_to", state.options.start_to));
-                        let builder =
-                            builder.query(&date_filter_to_query("e_from", state.options.end_from));
-                        let builder =
-                            builder.query(&date_filter_to_query("e_to", state.options.end_to));
-
-                        let resp = builder.send().await?;
-
-                        log::debug!("URL: {}", resp.url());
-
-                        let result = parser::parse_query(&resp.text().await?)?;
-
-                        let next_offset = match result.paging {
-                            None => None,
-                            Some(p) if p.to >= p.total => None,
-                            Some(p) => Some(p.to),
-                        };
-
-                        let context = (next.url.clone(), next.user.clone());
-
-                        let y = stream::iter(result.bookings)
-                            .map(move |b| {
-                                let mut b = b.clone();
-                                b.location = match &context.0 {
-                                    Ok(url) => make_url(&b.id, url, &context.1).ok(),
-                                    _ => None,
-                                };
-                                b
-                            })
-                            .map(|b| Ok(b));
-
-                        Ok(Some((
-                            y,
-                            ListState {
-                                offset: next_offset,
-                                ..next
-                            },
-                        )))
-                    }
+        let cursor = cursor.unwrap_or_default();
+        let page = match cursor.total {
+            Some(total) if cursor.offset >= total => return Ok((vec![], None)),
+            Some(total) => search.next_page(cursor.offset, total).await?,
+            None => search.page(cursor.offset).await?,
+        };
+
+        let next = match page.paging {
+            Some(p) if p.to < p.total => Some(Cursor {
+                offset: p.to,
+                total: Some(p.total),
+            }),
+            _ => None,
+        };
+
+        Ok((page.bookings, next))
+    }
+
+    /// List the resources (cars) of the club.
+    pub async fn list_resources(&self, user: User) -> Result<Vec<Resource>> {
+        let mut url = self.config.url.join("/buchung/room_list.php")?;
+        url.query_pairs_mut().append_pair("club", &user.club);
+
+        parser::parse_resources(
+            &self.fetch(self.request(Method::Get, url, &user)).await?,
+            &self.context(),
+        )
+    }
+
+    /// Get the busy and free times of a resource, within the provided time range.
+    pub async fn availability(
+        &self,
+        user: User,
+        resource: ResourceId,
+        range: Range<DateTime<Utc>>,
+    ) -> Result<Availability> {
+        check_range(&range)?;
+
+        let bookings = self
+            .bookings_in_range(
+                user,
+                &range,
+                ListOptions {
+                    resource: Some(resource),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        Ok(Availability::from_bookings(&range, &bookings))
+    }
+
+    /// Find all resources which are free for the whole time between `start` and `end`.
+    ///
+    /// This searches the bookings of each resource, up to `page_concurrency` at a time.
+    pub async fn find_free(
+        &self,
+        user: User,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Resource>> {
+        let range = start..end;
+        check_range(&range)?;
+
+        let resources = self.list_resources(user.clone()).await?;
+
+        // bookings only carry the name of the resource, so search the bookings of each resource
+        // by its id
+
+        stream::iter(resources)
+            .map(|r| {
+                let user = user.clone();
+                let range = range.clone();
+                async move {
+                    let free = self
+                        .availability(user, r.id.clone(), range)
+                        .await?
+                        .is_free();
+                    Result::Ok((r, free))
                 }
+            })
+            .buffered(self.config.page_concurrency.max(1))
+            .try_filter_map(|(r, free)| future::ready(Ok(Some(r).filter(|_| free))))
+            .try_collect()
+            .await
+    }
+
+    /// Fetch all active bookings which might overlap with the range.
+    async fn bookings_in_range(
+        &self,
+        user: User,
+        range: &Range<DateTime<Utc>>,
+        options: ListOptions,
+    ) -> Result<Vec<Booking>> {
+        // the filter works on dates in the local timezone, so add a day of margin
+
+        let day = Duration::days(1);
+
+        self.list_bookings(
+            user,
+            ListOptions {
+                start_to: Some(range.end.date() + day),
+                end_from: Some(range.start.date() - day),
+                state: BookingState::Active,
+                ..options
+            },
+        )
+        .try_collect()
+        .await
+    }
+
+    /// Get all details of a single booking.
+    pub async fn get_booking(&self, user: User, id: &str) -> Result<BookingDetails> {
+        let url = make_url(id, &self.config.url, &user)?;
+
+        let mut details = parser::parse_details(
+            id,
+            &self
+                .fetch(self.request(Method::Get, url.clone(), &user))
+                .await?,
+            &self.context(),
+        )?;
+        details.location = Some(url);
+
+        Ok(details)
+    }
+
+    /// Create a new booking.
+    ///
+    /// Times are truncated to minutes. If Elkato refuses the booking, this fails with
+    /// [`Error::Rejected`].
+    pub async fn create_booking(&self, user: User, booking: NewBooking) -> Result<Booking> {
+        let start = truncate_to_minute(booking.start);
+        let end = truncate_to_minute(booking.end);
+        let description = booking
+            .description
+            .as_deref()
+            .and_then(parser::normalize_description);
+
+        let form = vec![("create_by".to_string(), user.username.clone())];
+        let id = self
+            .submit_entry(
+                &user,
+                form,
+                booking.resource.as_ref(),
+                &start,
+                &end,
+                description.as_deref(),
+            )
+            .await?;
+
+        // the result page only links to the new entry at best, so we need to look it up
+
+        let day = Duration::days(1);
+        let candidates: Vec<Booking> = self
+            .list_bookings(
+                user.clone(),
+                ListOptions {
+                    owner: Some(user.username.as_str().into()),
+                    resource: Some(booking.resource.clone()),
+                    start_from: Some(start.date() - day),
+                    start_to: Some(start.date() + day),
+                    ..Default::default()
+                },
+            )
+            .try_collect()
+            .await?;
+
+        candidates
+            .into_iter()
+            .find(|b| match &id {
+                Some(id) => &b.id == id,
+                None => b.start == start && b.end == end && b.description == description,
+            })
+            // the booking was created, so this must not look like a missing resource
+            .ok_or_else(|| Error::Upstream("Unable to find the created booking".into()))
+    }
+
+    /// Update an existing booking.
+    ///
+    /// Extends, shortens, moves, or re-describes a booking. Values not set in `changes`, and all
+    /// other fields of the form, like the member the booking is for, are kept as they are. If
+    /// Elkato refuses the change, this fails with [`Error::Rejected`].
+    pub async fn update_booking(
+        &self,
+        user: User,
+        id: &str,
+        changes: BookingChanges,
+    ) -> Result<()> {
+        let mut url = self.config.url.join("/buchung/edit_entry.php")?;
+        url.query_pairs_mut()
+            .append_pair("club", &user.club)
+            .append_pair("id", id);
+
+        let current = parser::parse_edit_form(
+            &self.fetch(self.request(Method::Get, url, &user)).await?,
+            &self.context(),
+        )?;
+
+        let description = changes
+            .description
+            .unwrap_or(current.description)
+            .and_then(|desc| parser::normalize_description(&desc));
+        let mut form = current.fields;
+        form.retain(|(name, _)| name != "id");
+        form.push(("id".to_string(), id.to_string()));
+
+        self.submit_entry(
+            &user,
+            form,
+            &current.resource,
+            &changes.start.unwrap_or(current.start),
+            &changes.end.unwrap_or(current.end),
+            description.as_deref(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    fn context(&self) -> parser::Context {
+        parser::Context::new(&self.config)
+    }
+
+    /// Create an authenticated request.
+    fn request(&self, method: Method, url: Url, user: &User) -> Request {
+        let primary = self.config.language.split('-').next().unwrap_or_default();
+
+        let mut request = Request::new(method, url);
+        request.headers.push((
+            "Accept-Language".into(),
+            format!("{}, {};q=0.5", self.config.language, primary),
+        ));
+        request.auth = Some((user.username.clone(), user.password.clone()));
+        request
+    }
+
+    /// Send a request and return the body of the response.
+    async fn fetch(&self, request: Request) -> Result<String> {
+        let resp = self.transport.send(request).await?;
+
+        match resp.status {
+            401 | 403 => Err(Error::AuthenticationFailed),
+            200..=299 => Ok(resp.body),
+            status => Err(Error::UpstreamHttp(status)),
+        }
+    }
+
+    /// Submit the booking form, creating a new entry, or updating an existing one.
+    ///
+    /// The values are set on top of the fields of `form`, which contains the id of the entry when
+    /// updating. Returns the id of the entry, if the result page links to it.
+    async fn submit_entry(
+        &self,
+        user: &User,
+        mut form: Vec<(String, String)>,
+        resource: &str,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        description: Option<&str>,
+    ) -> Result<Option<String>> {
+        let url = self.config.url.join("/buchung/edit_entry_handler.php")?;
+
+        let mut values = vec![
+            ("club".to_string(), user.club.clone()),
+            ("room".to_string(), resource.to_string()),
+            (
+                "description".to_string(),
+                description.unwrap_or_default().to_string(),
+            ),
+        ];
+        values.extend(datetime_to_form("start", start, self.config.timezone));
+        values.extend(datetime_to_form("end", end, self.config.timezone));
+
+        for (name, value) in values {
+            match form.iter_mut().find(|(n, _)| *n == name) {
+                Some(field) => field.1 = value,
+                None => form.push((name, value)),
             }
-        })
-        .try_flatten()
+        }
+
+        let mut request = self.request(Method::Post, url, user);
+        request.form = form;
+
+        parser::parse_booking_response(&self.fetch(request).await?, &self.context())
+    }
+
+    /// Cancel a booking.
+    ///
+    /// Depending on the club's settings, Elkato either deletes the booking or marks it inactive.
+    /// Either way, the booking is read back afterwards to confirm it is no longer active.
+    pub async fn cancel_booking(&self, user: User, id: &str) -> Result<()> {
+        let mut url = self.config.url.join("/buchung/del_entry.php")?;
+        url.query_pairs_mut()
+            .append_pair("club", &user.club)
+            .append_pair("id", id)
+            .append_pair("series", "0");
+
+        parser::parse_booking_response(
+            &self.fetch(self.request(Method::Get, url, &user)).await?,
+            &self.context(),
+        )?;
+
+        let url = make_url(id, &self.config.url, &user)?;
+
+        match parser::parse_entry_state(
+            &self.fetch(self.request(Method::Get, url, &user)).await?,
+            &self.context(),
+        )? {
+            parser::EntryState::Active => Err(Error::Rejected(Rejection::Other(format!(
+                "Booking {} is still active",
+                id
+            )))),
+            parser::EntryState::Inactive
+            | parser::EntryState::Cancelled
+            | parser::EntryState::Missing => Ok(()),
+        }
+    }
+}
+
+/// A search for bookings, which can be run for any page.
+#[derive(Clone)]
+struct Search {
+    client: Client,
+    url: Url,
+    user: User,
+    options: ListOptions,
+    ctx: parser::Context,
+}
+
+impl Search {
+    /// Fetch the page starting at `offset`.
+    async fn page(&self, offset: usize) -> Result<parser::ListResponse> {
+        let options = &self.options;
+
+        let mut url = self.url.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("club", &self.user.club)
+                .append_pair("search_pos", &offset.to_string())
+                .append_pair(
+                    "sel_room",
+                    options.resource.as_ref().map_or("all", |r| r.as_ref()),
+                )
+                .append_pair(
+                    "sel_booker",
+                    options.booker.as_ref().map_or("all", |b| b.as_ref()),
+                )
+                .append_pair(
+                    "sel_owner",
+                    options.owner.as_ref().map_or("all", |o| o.as_ref()),
+                );
+
+            match &options.state {
+                BookingState::Active => query.append_pair("active", "on"),
+                // cancelled bookings are filtered from the inactive ones below
+                BookingState::Inactive | BookingState::Cancelled => {
+                    query.append_pair("inactive", "on")
+                }
+                BookingState::All => query
+                    .append_pair("active", "on")
+                    .append_pair("inactive", "on"),
+            };
+
+            query
+                .extend_pairs(date_filter_to_query("s_from", options.start_from))
+                .extend_pairs(date_filter_to_query("s_to", options.start_to))
+                .extend_pairs(date_filter_to_query("e_from", options.end_from))
+                .extend_pairs(date_filter_to_query("e_to", options.end_to));
+        }
+
+        let request = self.client.request(Method::Get, url, &self.user);
+        let mut result = parser::parse_query(&self.client.fetch(request).await?, &self.ctx)?;
+
+        result.bookings.retain(|b| options.state.includes(b.state));
+
+        for b in &mut result.bookings {
+            b.location = make_url(&b.id, &self.url, &self.user).ok();
+        }
+
+        Ok(result)
+    }
+
+    /// Fetch a later page, checking that it continues the results of the previous pages.
+    async fn next_page(&self, offset: usize, total: usize) -> Result<parser::ListResponse> {
+        let page = self.page(offset).await?;
+
+        match page.paging {
+            Some(p) if p.from == offset + 1 && p.total == total => Ok(page),
+            _ => Err(Error::Upstream(format!(
+                "Search results changed while paging, at offset {}",
+                offset
+            ))),
+        }
     }
 }
 
 /// Create the URL for a booking
-fn make_url(id: &str, url: &Url, user: &User) -> Result<Url, ParseError> {
+fn make_url(id: &str, url: &Url, user: &User) -> std::result::Result<Url, ParseError> {
     let mut url = url.join(&format!("/buchung/view_entry.php"))?;
 
     url.query_pairs_mut()
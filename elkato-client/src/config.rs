@@ -1,9 +1,35 @@
+use crate::locale::{Locale, LOCALES};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Config {
     pub url: Url,
+    /// The timezone Elkato shows times in.
+    pub timezone: Tz,
+    /// The preferred language of the pages, like `de-AT`.
+    pub language: String,
+    /// The locales pages may be served in.
+    ///
+    /// The language of each page is detected from this list, preferring the one matching
+    /// `language`.
+    pub locales: Vec<Locale>,
+    /// The number of result pages which may be fetched at the same time.
+    pub page_concurrency: usize,
+}
+
+impl Config {
+    /// Create a new configuration, using the timezone and language of German clubs.
+    pub fn new(url: Url) -> Self {
+        Config {
+            url,
+            timezone: chrono_tz::Europe::Berlin,
+            language: "de-DE".into(),
+            locales: LOCALES.to_vec(),
+            page_concurrency: 1,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors of the Elkato client.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Elkato rejected the credentials of the user.
+    #[error("Authentication failed")]
+    AuthenticationFailed,
+    /// Elkato is down for maintenance.
+    #[error("Elkato is in maintenance mode")]
+    Maintenance,
+    /// Elkato reported an internal error.
+    #[error("Upstream error: {0}")]
+    Upstream(String),
+    /// Elkato responded with an unexpected HTTP status code.
+    #[error("Upstream HTTP error: {0}")]
+    UpstreamHttp(u16),
+    /// A page returned by Elkato could not be parsed.
+    #[error("Failed to parse '{page}' at offset {offset}: {snippet}")]
+    Parse {
+        page: &'static str,
+        offset: usize,
+        snippet: String,
+    },
+    /// A page returned by Elkato lacks a required field.
+    #[error("Missing field '{field}' on '{page}'")]
+    MissingField { page: &'static str, field: String },
+    /// The provided options or configuration are invalid.
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+    /// The booking does not exist.
+    #[error("Booking not found: {0}")]
+    NotFound(String),
+    /// Elkato rejected a change to a booking.
+    #[error(transparent)]
+    Rejected(#[from] Rejection),
+    /// Failed to communicate with Elkato.
+    #[error("Transport error: {0}")]
+    Transport(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<url::ParseError> for Error {
+    fn from(err: url::ParseError) -> Self {
+        Error::InvalidOptions(format!("Invalid URL: {}", err))
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Transport(Box::new(err))
+    }
+}
+
+/// Reasons for Elkato to reject a change to a booking.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum Rejection {
+    /// The booking overlaps with an existing booking of the same resource.
+    #[error("Booking conflicts with an existing booking")]
+    Conflict,
+    /// The booking starts in the past.
+    #[error("Booking lies in the past")]
+    InPast,
+    /// The user is not allowed to book this resource.
+    #[error("Permission denied")]
+    PermissionDenied,
+    /// Any other message Elkato reported.
+    #[error("Booking rejected: {0}")]
+    Other(String),
+}
+
+/// The body of an error response of elkato-proxy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    /// The error, as shown to a user.
+    pub message: String,
+    /// The kind of the error, missing if the proxy failed without calling Elkato.
+    #[serde(flatten)]
+    pub kind: Option<ErrorKind>,
+}
+
+/// The kind of an [`Error`], with its details.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ErrorKind {
+    AuthenticationFailed,
+    Maintenance,
+    Upstream {
+        detail: String,
+    },
+    UpstreamHttp {
+        status: u16,
+    },
+    Parse {
+        page: String,
+        offset: usize,
+        snippet: String,
+    },
+    MissingField {
+        page: String,
+        field: String,
+    },
+    InvalidOptions {
+        detail: String,
+    },
+    NotFound {
+        detail: String,
+    },
+    Rejected {
+        rejection: RejectionKind,
+        /// The message of Elkato, for `other` rejections.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    Transport {
+        detail: String,
+    },
+}
+
+/// The kind of a [`Rejection`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionKind {
+    Conflict,
+    InPast,
+    PermissionDenied,
+    Other,
+}
+
+/// The pages the client parses, to restore the page of an error from its name.
+const PAGES: &[&str] = &["search", "room_list", "view_entry", "edit_entry"];
+
+impl From<&Error> for ErrorInfo {
+    fn from(err: &Error) -> Self {
+        let kind = match err {
+            Error::AuthenticationFailed => ErrorKind::AuthenticationFailed,
+            Error::Maintenance => ErrorKind::Maintenance,
+            Error::Upstream(detail) => ErrorKind::Upstream {
+                detail: detail.clone(),
+            },
+            Error::UpstreamHttp(status) => ErrorKind::UpstreamHttp { status: *status },
+            Error::Parse {
+                page,
+                offset,
+                snippet,
+            } => ErrorKind::Parse {
+                page: page.to_string(),
+                offset: *offset,
+                snippet: snippet.clone(),
+            },
+            Error::MissingField { page, field } => ErrorKind::MissingField {
+                page: page.to_string(),
+                field: field.clone(),
+            },
+            Error::InvalidOptions(detail) => ErrorKind::InvalidOptions {
+                detail: detail.clone(),
+            },
+            Error::NotFound(detail) => ErrorKind::NotFound {
+                detail: detail.clone(),
+            },
+            Error::Rejected(rejection) => {
+                let (rejection, detail) = match rejection {
+                    Rejection::Conflict => (RejectionKind::Conflict, None),
+                    Rejection::InPast => (RejectionKind::InPast, None),
+                    Rejection::PermissionDenied => (RejectionKind::PermissionDenied, None),
+                    Rejection::Other(detail) => (RejectionKind::Other, Some(detail.clone())),
+                };
+                ErrorKind::Rejected { rejection, detail }
+            }
+            Error::Transport(err) => ErrorKind::Transport {
+                detail: err.to_string(),
+            },
+        };
+
+        ErrorInfo {
+            message: err.to_string(),
+            kind: Some(kind),
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        let page = |name: String| PAGES.iter().find(|p| **p == name).map_or("unknown", |p| *p);
+
+        match kind {
+            ErrorKind::AuthenticationFailed => Error::AuthenticationFailed,
+            ErrorKind::Maintenance => Error::Maintenance,
+            ErrorKind::Upstream { detail } => Error::Upstream(detail),
+            ErrorKind::UpstreamHttp { status } => Error::UpstreamHttp(status),
+            ErrorKind::Parse {
+                page: name,
+                offset,
+                snippet,
+            } => Error::Parse {
+                page: page(name),
+                offset,
+                snippet,
+            },
+            ErrorKind::MissingField { page: name, field } => Error::MissingField {
+                page: page(name),
+                field,
+            },
+            ErrorKind::InvalidOptions { detail } => Error::InvalidOptions(detail),
+            ErrorKind::NotFound { detail } => Error::NotFound(detail),
+            ErrorKind::Rejected { rejection, detail } => Error::Rejected(match rejection {
+                RejectionKind::Conflict => Rejection::Conflict,
+                RejectionKind::InPast => Rejection::InPast,
+                RejectionKind::PermissionDenied => Rejection::PermissionDenied,
+                RejectionKind::Other => Rejection::Other(detail.unwrap_or_default()),
+            }),
+            ErrorKind::Transport { detail } => Error::Transport(detail.into()),
+        }
+    }
+}
@@ -4,12 +4,20 @@
 //! the system doesn't provide an APIs, and als the HTML code is way older than HTML4. So this
 //! crate uses 'nom' to scrape information from the generated pages.
 
-#[cfg(feature = "reqwest")]
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod client;
 mod config;
-#[cfg(feature = "reqwest")]
+mod error;
+pub mod locale;
 mod parser;
+mod proxy;
+mod service;
+pub mod transport;
 
-#[cfg(feature = "reqwest")]
 pub use client::*;
 pub use config::*;
+pub use error::*;
+pub use locale::Locale;
+pub use proxy::*;
+pub use service::*;
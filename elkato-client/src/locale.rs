@@ -0,0 +1,144 @@
+//! Text markers of the pages, for the languages Elkato is available in.
+
+/// Text markers of the pages, in a specific language.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Locale {
+    /// The primary language tag, like `de`.
+    pub language: &'static str,
+    /// The start of the paging information, like `Einträge 1 bis 20 von 45`.
+    pub entries: &'static str,
+    /// The word between the first and last entry of the paging information.
+    pub to: &'static str,
+    /// The word between the last entry and the total of the paging information.
+    pub of: &'static str,
+    /// Shown when a search has no results.
+    pub no_results: &'static str,
+    /// The heading of a page reporting an error.
+    pub error: &'static str,
+    /// Shown in the heading of the login page, when the password is wrong.
+    pub wrong_password: &'static str,
+    /// Shown in the heading of the page, when Elkato is under maintenance.
+    pub maintenance: &'static str,
+    /// Shown in the heading of the page, when Elkato fails to access its database.
+    pub database_error: &'static str,
+    /// Shown in the heading of the detail page, when the entry doesn't exist.
+    pub missing_entry: &'static [&'static str],
+    /// Shown when a booking collides with another one.
+    pub conflict: &'static str,
+    /// Shown when a booking starts in the past.
+    pub in_past: &'static str,
+    /// Shown when the user may not change a booking.
+    pub permission_denied: &'static str,
+    /// Values of the status of an entry which isn't active.
+    pub inactive: &'static [&'static str],
+    /// Values of the status of a cancelled entry, which is one way of being inactive.
+    pub cancelled: &'static [&'static str],
+    /// Labels of the detail page of an entry.
+    pub labels: Labels,
+}
+
+/// Labels of the detail page of an entry, without the trailing colon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Labels {
+    pub description: &'static str,
+    pub resource: &'static str,
+    pub station: &'static str,
+    pub start: &'static str,
+    pub end: &'static str,
+    pub booker: &'static str,
+    pub owner: &'static str,
+    pub notes: &'static str,
+    pub created: &'static str,
+    pub modified: &'static str,
+    pub status: &'static str,
+}
+
+pub const GERMAN: Locale = Locale {
+    language: "de",
+    entries: "<B>Eintr&auml;ge",
+    to: "bis",
+    of: "von",
+    no_results: "<B>Die Suche ergab keine Treffer!</B>",
+    error: "<H1>Fehler",
+    wrong_password: "Passwort falsch",
+    maintenance: "Wartungsarbeiten",
+    database_error: "Datenbankfehler",
+    missing_entry: &["Ung&uuml;ltige Eintrags-ID", "existiert nicht"],
+    conflict: "kollidieren",
+    in_past: "in der Vergangenheit",
+    permission_denied: "Zugriff verweigert",
+    inactive: &["storniert", "inaktiv"],
+    cancelled: &["storniert"],
+    labels: Labels {
+        description: "Beschreibung",
+        resource: "Fahrzeug",
+        station: "Standort",
+        start: "Beginn",
+        end: "Ende",
+        booker: "Gebucht von",
+        owner: "Gebucht für",
+        notes: "Bemerkung",
+        created: "Erstellt",
+        modified: "Geändert",
+        status: "Status",
+    },
+};
+
+pub const ENGLISH: Locale = Locale {
+    language: "en",
+    entries: "<B>Records",
+    to: "through",
+    of: "of",
+    no_results: "<B>No matching entries found!</B>",
+    error: "<H1>Error",
+    wrong_password: "Wrong password",
+    maintenance: "Maintenance",
+    database_error: "Database error",
+    missing_entry: &["Invalid entry ID", "does not exist"],
+    conflict: "conflict",
+    in_past: "in the past",
+    permission_denied: "Access denied",
+    inactive: &["cancelled", "inactive"],
+    cancelled: &["cancelled"],
+    labels: Labels {
+        description: "Description",
+        resource: "Vehicle",
+        station: "Location",
+        start: "Start",
+        end: "End",
+        booker: "Booked by",
+        owner: "Booked for",
+        notes: "Notes",
+        created: "Created",
+        modified: "Modified",
+        status: "Status",
+    },
+};
+
+/// All locales shipped with this crate.
+pub const LOCALES: &[Locale] = &[GERMAN, ENGLISH];
+
+impl Locale {
+    /// Find the locale for a language tag, like `de-AT`.
+    pub fn for_language(language: &str) -> Option<&'static Locale> {
+        LOCALES.iter().find(|l| l.is_language(language))
+    }
+
+    /// Check if the locale is for the provided language tag, like `de-AT`.
+    pub fn is_language(&self, language: &str) -> bool {
+        let primary = language.split('-').next().unwrap_or_default();
+        self.language.eq_ignore_ascii_case(primary)
+    }
+
+    /// Detect the locale of a page, from a list of candidates.
+    ///
+    /// Search results, detail pages, and error pages can be detected.
+    pub fn detect<'a>(locales: &'a [Locale], body: &str) -> Option<&'a Locale> {
+        locales.iter().find(|l| {
+            body.contains(l.entries)
+                || body.contains(l.no_results)
+                || body.contains(l.error)
+                || body.contains(&format!("<B>{}:</B>", l.labels.resource))
+        })
+    }
+}
@@ -1,82 +1,358 @@
+use nom::alt;
+use nom::call;
 use nom::complete;
+use nom::cond;
 use nom::do_parse;
 use nom::many0;
 use nom::many_till;
+use nom::map;
+use nom::map_opt;
 use nom::named;
+use nom::named_args;
 use nom::one_of;
 use nom::opt;
 use nom::tag;
+use nom::take_till1;
 use nom::take_until;
+use nom::take_while1;
+use nom::verify;
 use nom::IResult;
 
-use anyhow::anyhow;
+use std::collections::HashMap;
 
-use chrono::{DateTime, Utc};
-use elkato_common::data::Booking;
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, Utc};
+
+use crate::config::Config;
+use crate::error::{Error, Rejection, Result};
+use crate::locale::{Locale, GERMAN};
+use elkato_common::data::{Booking, BookingDetails, BookingState, Member, Resource};
 use nom::character::complete::digit1;
+use nom::error::ErrorKind;
+
+use chrono::{Offset, TimeZone};
+use chrono_tz::Tz;
+
+/// Settings for parsing pages.
+#[derive(Clone, Debug)]
+pub struct Context {
+    pub timezone: Tz,
+    /// Candidate locales, the preferred one first.
+    pub locales: Vec<Locale>,
+}
+
+impl Context {
+    pub fn new(config: &Config) -> Self {
+        let mut locales = config.locales.clone();
+        // stable sort, keeping the configured order otherwise
+        locales.sort_by_key(|l| !l.is_language(&config.language));
+
+        Context {
+            timezone: config.timezone,
+            locales,
+        }
+    }
+
+    /// Detect the locale of a page, falling back to the preferred locale.
+    fn locale(&self, body: &str) -> &Locale {
+        Locale::detect(&self.locales, body)
+            .or_else(|| self.locales.first())
+            .unwrap_or(&GERMAN)
+    }
+
+    /// The candidate locales, for markers which can't be used for detecting the locale.
+    fn candidates(&self) -> &[Locale] {
+        if self.locales.is_empty() {
+            std::slice::from_ref(&GERMAN)
+        } else {
+            &self.locales
+        }
+    }
+}
+
+/// Create a parse error, pointing at the position nom failed at.
+fn parse_error(
+    page: &'static str,
+    body: &str,
+    err: nom::Err<(&str, nom::error::ErrorKind)>,
+) -> Error {
+    log::debug!("Parse failure: {:?}", err);
+
+    let offset = match err {
+        nom::Err::Error((rem, _)) | nom::Err::Failure((rem, _)) => body.len() - rem.len(),
+        nom::Err::Incomplete(_) => body.len(),
+    };
+
+    Error::Parse {
+        page,
+        offset,
+        snippet: body[offset..].chars().take(80).collect(),
+    }
+}
+
+named_args!(
+    element<'a>(start: &'a str, end: &'a str)<&'a str, &'a str>,
+    do_parse!(
+        take_until!(start) >> tag!(start) >> text: take_until!(end) >>
+        (text)
+    )
+);
+
+fn page_title(body: &str) -> IResult<&str, &str> {
+    element(body, "<TITLE>", "</TITLE>")
+}
+
+/// The title and the first headings of a page.
+///
+/// Markers are only looked for in there, as the rest of the page may contain text entered by
+/// members, like the description of a booking.
+fn page_headings(body: &str) -> Vec<&str> {
+    [
+        ("<TITLE>", "</TITLE>"),
+        ("<H1>", "</H1>"),
+        ("<H2>", "</H2>"),
+    ]
+    .iter()
+    .filter_map(|(start, end)| element(body, start, end).ok())
+    .map(|(_, text)| text)
+    .collect()
+}
+
+/// Check if the title or a heading of the page shows a marker, in any of the candidate locales.
+fn shows<F>(headings: &[&str], ctx: &Context, marker: F) -> bool
+where
+    F: Fn(&Locale) -> &str,
+{
+    ctx.candidates().iter().any(|locale| {
+        headings
+            .iter()
+            .any(|heading| heading.contains(marker(locale)))
+    })
+}
+
+/// Check if Elkato served a login, maintenance, or error page, instead of the requested page.
+fn check_page(body: &str, ctx: &Context) -> Result<()> {
+    let headings = page_headings(body);
+
+    if body.contains("name=\"NewUserPassword\"") || shows(&headings, ctx, |l| l.wrong_password) {
+        return Err(Error::AuthenticationFailed);
+    }
+
+    if shows(&headings, ctx, |l| l.maintenance) {
+        return Err(Error::Maintenance);
+    }
+
+    // PHP errors are printed as markup, which can't be part of any escaped text
+    if shows(&headings, ctx, |l| l.database_error) || body.contains("<b>Fatal error</b>") {
+        let message = page_title(body)
+            .ok()
+            .and_then(|(_, title)| parse_description(title))
+            .unwrap_or_else(|| "Unknown error".into());
+        return Err(Error::Upstream(message));
+    }
+
+    Ok(())
+}
 
-use chrono::TimeZone;
-use chrono_tz::Europe::Berlin;
+fn parse_number<T: std::str::FromStr>(input: &str) -> IResult<&str, T> {
+    let (rem, digits) = digit1(input)?;
+    match digits.parse() {
+        Ok(value) => Ok((rem, value)),
+        Err(_) => Err(nom::Err::Error((input, ErrorKind::Digit))),
+    }
+}
 
 fn parse_int(input: &str) -> IResult<&str, u32> {
-    let (input, digits) = digit1(input)?;
-    IResult::Ok((input, u32::from_str_radix(digits, 10).unwrap()))
+    parse_number(input)
 }
 
 fn parse_usize(input: &str) -> IResult<&str, usize> {
-    let (input, digits) = digit1(input)?;
-    IResult::Ok((input, usize::from_str_radix(digits, 10).unwrap()))
+    parse_number(input)
 }
 
-named!(date<&str,DateTime<Utc>>,
-    do_parse!(
-        day: parse_int >> tag!(".") >> month: parse_int >> tag!(".") >> year: parse_int >> tag!(", ") >>
-        hour: parse_int >> tag!(":") >> minute: parse_int >>
-        (
-            Berlin.ymd(2000 + year as i32, month, day).and_hms(hour, minute, 0).with_timezone(&Utc)
-        )
-        )
+/// Parse a year, with either two or four digits.
+fn parse_year(input: &str) -> IResult<&str, i32> {
+    let (rem, digits) = digit1(input)?;
+    match (digits.len(), digits.parse::<i32>()) {
+        (2, Ok(year)) => Ok((rem, 2000 + year)),
+        (4, Ok(year)) => Ok((rem, year)),
+        _ => Err(nom::Err::Error((input, ErrorKind::Digit))),
+    }
+}
+
+/// Convert a local date and time, as shown by Elkato, to UTC.
+///
+/// Times which are ambiguous, due to the switch back from daylight saving time, resolve to the
+/// earlier instant. Times which don't exist, due to the switch to daylight saving time, are
+/// moved forward by the length of the gap, by using the offset from before the gap. Invalid
+/// dates result in `None`.
+fn local_datetime(
+    tz: Tz,
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+) -> Option<DateTime<Utc>> {
+    let local = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, 0)?;
+
+    match tz.from_local_datetime(&local) {
+        LocalResult::None => {
+            // gaps are much shorter than a day
+            let before = tz
+                .offset_from_local_datetime(&(local - Duration::days(1)))
+                .earliest()?
+                .fix();
+            Some(DateTime::from_utc(local - before, Utc))
+        }
+        result => result.earliest().map(|date| date.with_timezone(&Utc)),
+    }
+}
+
+named_args!(date(tz: Tz)<&str, DateTime<Utc>>,
+    map_opt!(
+        do_parse!(
+            day: parse_int >> tag!(".") >> month: parse_int >> tag!(".") >> year: parse_year >> tag!(", ") >>
+            hour: parse_int >> tag!(":") >> minute: parse_int >>
+            ((year, month, day, hour, minute))
+        ),
+        |(year, month, day, hour, minute)| local_datetime(tz, year, month, day, hour, minute)
+    )
 );
 
 named!(space<&str, Vec<char>>,
      many0!(one_of!("\t\n\r "))
 );
 
-named!(style<&str,()>, do_parse!(
-    tag!("style=\"") >> take_until!("\"") >> tag!("\"") >> ()
+named!(style<&str,&str>, do_parse!(
+    tag!("style=\"") >> style: take_until!("\"") >> tag!("\"") >> (style)
 ));
 
+named!(title<&str,&str>, do_parse!(
+    take_until!("title=\"") >> tag!("title=\"") >> title: take_until!("\"") >> tag!("\"") >> (title)
+));
+
+/// Parse a member, shown either by the login name, or as `Display Name (login)`.
+fn parse_member(text: &str) -> Member {
+    let text = text.trim();
+    if let Some(rest) = text.strip_suffix(')') {
+        if let Some(pos) = rest.rfind(" (") {
+            let (name, id) = (rest[..pos].trim(), &rest[pos + 2..]);
+            if !name.is_empty() && !id.is_empty() {
+                return Member {
+                    id: id.into(),
+                    name: Some(name.into()),
+                };
+            }
+        }
+    }
+
+    Member {
+        id: text.into(),
+        name: None,
+    }
+}
+
+named!(
+    member_cell<&str, Member>,
+    do_parse!(
+        space >> tag!("<TD nowrap>") >> text: take_until!("</TD>") >> tag!("</TD>\n") >>
+        (parse_member(&htmlescape::decode_html(text).unwrap_or_else(|_| text.into())))
+    )
+);
+
+/// The state of an entry, from the style of its date cells.
+///
+/// Entries which are cancelled are crossed out, other inactive entries are greyed out.
+fn style_state(styles: &[&str]) -> BookingState {
+    if styles.iter().any(|s| s.contains("line-through")) {
+        BookingState::Cancelled
+    } else if styles.iter().any(|s| s.contains("#cccccc")) {
+        BookingState::Inactive
+    } else {
+        BookingState::Active
+    }
+}
+
+/// Get the text content of a table cell, dropping all tags.
+fn cell_text(cell: &str) -> Option<String> {
+    let mut text = String::with_capacity(cell.len());
+    let mut in_tag = false;
+    for c in cell.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    parse_description(&text)
+}
+
+/// Get the flags of the details cell, which are images with a title.
+fn cell_flags(cell: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+
+    let mut input = cell;
+    while let Ok((rem, flag)) = title(input) {
+        flags.extend(parse_description(flag));
+        input = rem;
+    }
+
+    flags
+}
+
 fn parse_description(desc: &str) -> Option<String> {
     htmlescape::decode_html(&desc)
         .ok()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
+        .and_then(|s| normalize_description(&s))
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Normalize a description the way Elkato shows it, dropping surrounding whitespace.
+pub fn normalize_description(desc: &str) -> Option<String> {
+    Some(desc.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// The most pages a search may have. A larger total is rejected as implausible.
+const MAX_PAGES: usize = 1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Paging {
     pub from: usize,
     pub to: usize,
     pub total: usize,
 }
 
-named!(
-    paging<&str,Paging>,
+impl Paging {
+    /// The number of entries per page.
+    pub fn page_size(&self) -> usize {
+        (self.to + 1).saturating_sub(self.from).max(1)
+    }
+
+    fn is_plausible(&self) -> bool {
+        self.from <= self.to + 1
+            && self.to <= self.total
+            && self.total - self.to <= self.page_size() * MAX_PAGES
+    }
+}
+
+named_args!(
+    paging<'a>(locale: &'a Locale)<&'a str, Paging>,
     do_parse!(
-        take_until!("<B>Eintr&auml;ge") >> tag!("<B>Eintr&auml;ge") >>
+        take_until!(locale.entries) >> tag!(locale.entries) >>
         space >> from: parse_usize >>
-        space >> tag!("bis") >> space >> to: parse_usize >>
-        space >> tag!("von") >> space >> total: parse_usize >> tag!("<BR>") >>
-        (
-            Paging{
-                from, to, total,
-            }
-        )
+        space >> tag!(locale.to) >> space >> to: parse_usize >>
+        space >> tag!(locale.of) >> space >>
+        paging: verify!(
+            map!(parse_usize, |total| Paging{ from, to, total }),
+            Paging::is_plausible
+        ) >>
+        tag!("<BR>") >>
+        (paging)
     )
 );
 
-named!(
-    result_entry<&str, Booking>,
+named_args!(
+    result_entry(tz: Tz)<&str, Booking>,
     do_parse!(
         take_until!("<TR >") >>
         tag!("<TR >\n") >>
@@ -85,20 +361,20 @@ named!(
         space >> tag!("<TD align=right>") >> id: take_until!("</TD>") >> tag!("</TD>\n") >>
         // car
         space >> tag!("<TD nowrap>") >> resource: take_until!("</TD>") >> tag!("</TD>\n") >>
-        // user
-        space >> tag!("<TD nowrap>") >> user: take_until!("</TD>") >> tag!("</TD>\n") >>
+        // user, optionally preceded by the member who made the booking
+        first: member_cell >> second: opt!(complete!(member_cell)) >>
 
         // start time
-        space >> tag!("<TD") >> space >> style >> tag!(">") >> space >> take_until!("<TD") >>
-        space >> tag!("<TD nowrap") >> space >> style >> tag!(">") >> space >> start: date >> tag!("</TD>\n") >>
+        space >> tag!("<TD") >> space >> style1: style >> tag!(">") >> space >> take_until!("<TD") >>
+        space >> tag!("<TD nowrap") >> space >> style2: style >> tag!(">") >> space >> start: call!(date, tz) >> tag!("</TD>\n") >>
 
         // end time
-        space >> tag!("<TD") >> space >> style >> tag!(">") >> space >> take_until!("<TD") >>
-        space >> tag!("<TD nowrap") >> space >> style >> tag!(">") >> space >> end: date >> tag!("</TD>\n") >>
+        space >> tag!("<TD") >> space >> style3: style >> tag!(">") >> space >> take_until!("<TD") >>
+        space >> tag!("<TD nowrap") >> space >> style4: style >> tag!(">") >> space >> end: call!(date, tz) >> tag!("</TD>\n") >>
 
         // duration & details
-        space >> take_until!("</TD>") >> tag!("</TD>\n") >>
-        space >> take_until!("</TD>") >> tag!("</TD>\n") >>
+        space >> duration: take_until!("</TD>") >> tag!("</TD>\n") >>
+        space >> details: take_until!("</TD>") >> tag!("</TD>\n") >>
 
         // description
         space >> tag!("<TD nowrap>") >> description: take_until!("</TD>") >> tag!("</TD>\n") >>
@@ -109,21 +385,58 @@ named!(
                 id: id.into(),
                 location: Default::default(),
                 resource: resource.into(),
-                user: user.into(),
+                booker: second.as_ref().map(|_| first.clone()),
+                owner: second.unwrap_or(first),
                 start: start,
                 end: end,
                 description: parse_description(description),
+                duration: cell_text(duration),
+                flags: cell_flags(details),
+                state: style_state(&[style1, style2, style3, style4]),
             }
         )
     )
 );
 
+named!(
+    resource_entry<&str, Resource>,
+    do_parse!(
+        take_until!("<TR >") >>
+        tag!("<TR >\n") >>
+
+        space >> tag!("<TD align=right>") >> id: take_until!("</TD>") >> tag!("</TD>\n") >>
+        space >> tag!("<TD nowrap>") >> name: take_until!("</TD>") >> tag!("</TD>\n") >>
+        space >> tag!("<TD nowrap>") >> station: take_until!("</TD>") >> tag!("</TD>\n") >>
+        space >> tag!("<TD nowrap>") >> category: take_until!("</TD>") >> tag!("</TD>\n") >>
+        space >> tag!("</TR>") >> space >>
+
+        (
+            Resource {
+                id: id.trim().into(),
+                name: parse_description(name).unwrap_or_default(),
+                station: parse_description(station),
+                category: parse_description(category),
+            }
+        )
+    )
+);
+
+named!(all_resources<&str, Vec<Resource>>,
+    do_parse!(
+        // skip the header, so that an empty table ends right after it
+        take_until!("</TR>") >> tag!("</TR>") >> space >>
+        entries: many_till!(resource_entry, tag!("</TABLE>")) >>
+        (entries.0)
+    )
+);
+
 // named!(all_entries<&str, Vec<Booking>>, many0!(result_entry));
 
-named!(all_entries<&str, (Option<Paging>,Vec<Booking>)>,
+named_args!(all_entries<'a>(tz: Tz, locale: &'a Locale, paged: bool)<&'a str, (Option<Paging>,Vec<Booking>)>,
     do_parse!(
-        paging: opt!(complete!(paging)) >>
-        entries: many_till!(result_entry, tag!("</TABLE>")) >>
+        // when shown, the paging information must be valid
+        paging: cond!(paged, call!(paging, locale)) >>
+        entries: many_till!(call!(result_entry, tz), tag!("</TABLE>")) >>
         (
             (paging, entries.0)
         )
@@ -136,17 +449,23 @@ pub struct ListResponse {
     pub bookings: Vec<Booking>,
 }
 
-pub fn parse_query(body: &String) -> anyhow::Result<ListResponse> {
+pub fn parse_query(body: &str, ctx: &Context) -> Result<ListResponse> {
     log::debug!("Payload: {}", body);
 
-    if body.contains("<B>Die Suche ergab keine Treffer!</B>") {
+    check_page(body, ctx)?;
+
+    let locale = ctx.locale(body);
+
+    if body.contains(locale.no_results) {
         return Ok(ListResponse {
             paging: None,
             bookings: vec![],
         });
     }
 
-    match all_entries(&body) {
+    let paged = body.contains(locale.entries);
+
+    match all_entries(&body, ctx.timezone, locale, paged) {
         Ok(result) => {
             // the let isn't necessary, but works around a parser issue of IntelliJ and rustfmt
             // when using "result.1.0", where "1.0" is interpreted as an float, rather than two
@@ -157,12 +476,856 @@ pub fn parse_query(body: &String) -> anyhow::Result<ListResponse> {
                 bookings: r.1,
             })
         }
-        e => {
-            log::debug!("Payload: {}", body);
-            log::debug!("Parse failure: {:?}", e);
+        Err(err) => Err(parse_error("search", body, err)),
+    }
+}
+
+named!(error_message<&str, &str>,
+    do_parse!(
+        take_until!("<H2>") >> tag!("<H2>") >> message: take_until!("</H2>") >>
+        (message)
+    )
+);
+
+named!(entry_link<&str, &str>,
+    do_parse!(
+        take_until!("view_entry.php?") >> tag!("view_entry.php?") >> query: take_until!("\"") >>
+        (query)
+    )
+);
+
+/// Find the id of the entry a page links or redirects to.
+fn entry_id(body: &str) -> Option<String> {
+    let (_, query) = entry_link(body).ok()?;
+    query
+        .split("&amp;")
+        .flat_map(|param| param.split('&'))
+        .find_map(|param| param.strip_prefix("id="))
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+}
+
+/// Check the result page of a booking form submission for an error.
+///
+/// Returns the id of the entry, if the page links to it.
+pub fn parse_booking_response(body: &str, ctx: &Context) -> Result<Option<String>> {
+    log::debug!("Payload: {}", body);
+
+    check_page(body, ctx)?;
+
+    let locale = ctx.locale(body);
+    let headings = page_headings(body);
+    let shows = |marker: &str| headings.iter().any(|heading| heading.contains(marker));
+
+    if shows(locale.conflict) {
+        return Err(Rejection::Conflict.into());
+    }
+    if shows(locale.in_past) {
+        return Err(Rejection::InPast.into());
+    }
+    if shows(locale.permission_denied) {
+        return Err(Rejection::PermissionDenied.into());
+    }
+
+    if body.contains(locale.error) {
+        let message = error_message(body)
+            .ok()
+            .and_then(|(_, message)| parse_description(message))
+            .unwrap_or_else(|| "Unknown error".into());
+        return Err(Rejection::Other(message).into());
+    }
+
+    Ok(entry_id(body))
+}
+
+pub fn parse_resources(body: &str, ctx: &Context) -> Result<Vec<Resource>> {
+    log::debug!("Payload: {}", body);
+
+    check_page(body, ctx)?;
+
+    all_resources(body)
+        .map(|(_, resources)| resources)
+        .map_err(|err| parse_error("room_list", body, err))
+}
+
+/// State of a booking, as shown on its detail page.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EntryState {
+    Active,
+    Inactive,
+    Cancelled,
+    Missing,
+}
+
+pub fn parse_entry_state(body: &str, ctx: &Context) -> Result<EntryState> {
+    log::debug!("Payload: {}", body);
+
+    check_page(body, ctx)?;
+
+    let headings = page_headings(body);
+    let missing = ctx.candidates().iter().any(|locale| {
+        locale
+            .missing_entry
+            .iter()
+            .any(|marker| headings.iter().any(|heading| heading.contains(marker)))
+    });
+    if missing {
+        return Ok(EntryState::Missing);
+    }
+
+    // only the status of the entry, as the description may contain anything
+    let locale = ctx.locale(body);
+    let status = detail_rows(body)
+        .remove(locale.labels.status)
+        .ok_or_else(|| Error::MissingField {
+            page: "view_entry",
+            field: locale.labels.status.into(),
+        })?
+        .to_lowercase();
+
+    let shows = |words: &[&str]| words.iter().any(|word| status.contains(word));
+
+    Ok(if shows(locale.cancelled) {
+        EntryState::Cancelled
+    } else if shows(locale.inactive) {
+        EntryState::Inactive
+    } else {
+        EntryState::Active
+    })
+}
+
+named!(attribute_value<&str, &str>,
+    alt!(
+        do_parse!(tag!("\"") >> value: take_until!("\"") >> tag!("\"") >> (value)) |
+        take_till1!(|c: char| c.is_whitespace() || c == '>')
+    )
+);
+
+named!(attribute<&str, (&str, Option<&str>)>,
+    do_parse!(
+        space >>
+        name: take_while1!(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-') >>
+        value: opt!(complete!(do_parse!(tag!("=") >> value: attribute_value >> (value)))) >>
+        ((name, value))
+    )
+);
+
+// The attributes of an input field, in any order.
+named!(input_field<&str, Vec<(&str, Option<&str>)>>,
+    do_parse!(
+        tag!("<INPUT") >> attributes: many0!(complete!(attribute)) >> space >> tag!(">") >>
+        (attributes)
+    )
+);
+
+/// Get the value an input field submits, if it has a name, and is checked when it can be.
+fn input_value<'a>(attributes: &[(&'a str, Option<&'a str>)]) -> Option<(&'a str, &'a str)> {
+    let find = |name: &str| {
+        attributes
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.unwrap_or_default())
+    };
+
+    let name = find("name").filter(|name| !name.is_empty())?;
+    let kind = find("type").unwrap_or("text").to_lowercase();
+
+    match kind.as_str() {
+        "checkbox" | "radio" => {
+            find("checked")?;
+            Some((name, find("value").unwrap_or("on")))
+        }
+        "submit" | "reset" | "button" | "image" | "file" => None,
+        _ => Some((name, find("value").unwrap_or_default())),
+    }
+}
+
+named!(select_field<&str, (&str, &str)>,
+    do_parse!(
+        tag!("<SELECT name=\"") >> name: take_until!("\"") >> tag!("\"") >>
+        take_until!(">") >> tag!(">") >> options: take_until!("</SELECT>") >>
+        ((name, options))
+    )
+);
+
+named!(selected_option<&str, &str>,
+    do_parse!(
+        tag!("<OPTION value=\"") >> value: take_until!("\"") >> tag!("\" selected>") >>
+        (value)
+    )
+);
+
+/// Get the value of the selected option.
+fn selected_value(options: &str) -> Option<&str> {
+    let mut input = options;
+    while let Some(pos) = input.find("<OPTION value=\"") {
+        input = &input[pos..];
+        if let Ok((_, value)) = selected_option(input) {
+            return Some(value);
+        }
+        input = &input[1..];
+    }
+    None
+}
+
+/// Values of the form for editing a booking.
+#[derive(Clone, Debug)]
+pub struct EditForm {
+    pub resource: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub description: Option<String>,
+    /// All fields of the form, decoded, so that they can be sent back as they are.
+    pub fields: Vec<(String, String)>,
+}
+
+/// Get the values the form submits: those of all input fields, skipping unchecked checkboxes
+/// and radio buttons, and the selected values of all select fields.
+fn form_values(body: &str) -> Vec<(&str, &str)> {
+    let mut values = vec![];
+
+    let mut input = body;
+    while let Some(pos) = input.find("<INPUT") {
+        input = &input[pos..];
+        match input_field(input) {
+            Ok((rem, attributes)) => {
+                values.extend(input_value(&attributes));
+                input = rem;
+            }
+            _ => input = &input[1..],
+        }
+    }
+
+    let mut input = body;
+    while let Some(pos) = input.find("<SELECT name=\"") {
+        input = &input[pos..];
+        match select_field(input) {
+            Ok((rem, (name, options))) => {
+                if let Some(value) = selected_value(options) {
+                    values.push((name, value));
+                }
+                input = rem;
+            }
+            _ => input = &input[1..],
+        }
+    }
 
-            //Err(e.context("Failed to parse"))
-            Err(anyhow!("Failed to parse"))
+    values
+}
+
+fn form_datetime(values: &HashMap<&str, &str>, prefix: &str, tz: Tz) -> Result<DateTime<Utc>> {
+    let value = |name: &str| -> Result<u32> {
+        let name = format!("{}_{}", prefix, name);
+        let value = values
+            .get(name.as_str())
+            .ok_or_else(|| Error::MissingField {
+                page: "edit_entry",
+                field: name.clone(),
+            })?;
+        value.parse().map_err(|_| Error::Parse {
+            page: "edit_entry",
+            offset: 0,
+            snippet: format!("{}={}", name, value),
+        })
+    };
+
+    local_datetime(
+        tz,
+        value("year")? as i32,
+        value("month")?,
+        value("day")?,
+        value("hour")?,
+        value("minute")?,
+    )
+    .ok_or_else(|| Error::Parse {
+        page: "edit_entry",
+        offset: 0,
+        snippet: format!("Invalid date: {}", prefix),
+    })
+}
+
+pub fn parse_edit_form(body: &str, ctx: &Context) -> Result<EditForm> {
+    log::debug!("Payload: {}", body);
+
+    check_page(body, ctx)?;
+
+    let fields: Vec<(String, String)> = form_values(body)
+        .into_iter()
+        .map(|(name, value)| {
+            let value = htmlescape::decode_html(value).unwrap_or_else(|_| value.into());
+            (name.to_string(), value)
+        })
+        .collect();
+    let values: HashMap<&str, &str> = fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+
+    let resource = values
+        .get("room")
+        .ok_or_else(|| Error::MissingField {
+            page: "edit_entry",
+            field: "room".into(),
+        })?
+        .to_string();
+
+    Ok(EditForm {
+        resource,
+        start: form_datetime(&values, "start", ctx.timezone)?,
+        end: form_datetime(&values, "end", ctx.timezone)?,
+        description: values
+            .get("description")
+            .and_then(|desc| normalize_description(desc)),
+        fields,
+    })
+}
+
+named!(detail_row<&str, (&str, &str)>,
+    do_parse!(
+        tag!("<TR>") >> space >>
+        tag!("<TD><B>") >> label: take_until!(":</B></TD>") >> tag!(":</B></TD>") >> space >>
+        tag!("<TD>") >> value: take_until!("</TD>") >> tag!("</TD>") >> space >>
+        tag!("</TR>") >>
+        ((label, value))
+    )
+);
+
+fn detail_rows(body: &str) -> HashMap<String, String> {
+    let mut rows = HashMap::new();
+
+    let mut input = body;
+    while let Some(pos) = input.find("<TR>") {
+        input = &input[pos..];
+        match detail_row(input) {
+            Ok((rem, (label, value))) => {
+                if let (Some(label), Some(value)) =
+                    (parse_description(label), parse_description(value))
+                {
+                    rows.insert(label, value);
+                }
+                input = rem;
+            }
+            _ => input = &input[1..],
         }
     }
+
+    rows
+}
+
+pub fn parse_details(id: &str, body: &str, ctx: &Context) -> Result<BookingDetails> {
+    log::debug!("Payload: {}", body);
+
+    let state = match parse_entry_state(body, ctx)? {
+        EntryState::Active => BookingState::Active,
+        EntryState::Inactive => BookingState::Inactive,
+        EntryState::Cancelled => BookingState::Cancelled,
+        EntryState::Missing => return Err(Error::NotFound(id.into())),
+    };
+
+    let labels = &ctx.locale(body).labels;
+    let mut rows = detail_rows(body);
+
+    let text = |rows: &mut HashMap<String, String>, label: &str| {
+        rows.remove(label).ok_or_else(|| Error::MissingField {
+            page: "view_entry",
+            field: label.into(),
+        })
+    };
+    let parse_time = |value: String| match date(&value, ctx.timezone) {
+        Ok((_, date)) => Ok(date),
+        Err(err) => Err(parse_error("view_entry", &value, err)),
+    };
+    let time =
+        |rows: &mut HashMap<String, String>, label: &str| text(rows, label).and_then(parse_time);
+    // missing is fine, but when present, it must be valid
+    let optional_time = |rows: &mut HashMap<String, String>, label: &str| {
+        rows.remove(label).map(parse_time).transpose()
+    };
+
+    Ok(BookingDetails {
+        id: id.into(),
+        resource: text(&mut rows, labels.resource)?,
+        station: text(&mut rows, labels.station).ok(),
+        booker: text(&mut rows, labels.booker).map(|s| parse_member(&s))?,
+        owner: text(&mut rows, labels.owner).map(|s| parse_member(&s))?,
+        start: time(&mut rows, labels.start)?,
+        end: time(&mut rows, labels.end)?,
+        description: text(&mut rows, labels.description).ok(),
+        notes: text(&mut rows, labels.notes).ok(),
+        created: optional_time(&mut rows, labels.created)?,
+        modified: optional_time(&mut rows, labels.modified)?,
+        location: None,
+        state,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!("../tests/fixtures/search/", $name, ".html"))
+        };
+        ($dir:literal, $name:literal) => {
+            include_str!(concat!("../tests/fixtures/", $dir, "/", $name, ".html"))
+        };
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn ctx() -> Context {
+        Context::new(&Config::new("https://www.elkato.de".parse().unwrap()))
+    }
+
+    fn date(input: &str) -> IResult<&str, DateTime<Utc>> {
+        super::date(input, chrono_tz::Europe::Berlin)
+    }
+
+    /// The parts of a booking the parser is responsible for.
+    fn summary(b: &Booking) -> (&str, &str, &str, DateTime<Utc>, DateTime<Utc>, Option<&str>) {
+        (
+            &b.id,
+            &b.resource,
+            b.owner.id.as_ref(),
+            b.start,
+            b.end,
+            b.description.as_deref(),
+        )
+    }
+
+    #[test]
+    fn test_no_results() {
+        let result = parse_query(fixture!("no_results"), &ctx()).unwrap();
+
+        assert_eq!(result.paging, None);
+        assert!(result.bookings.is_empty());
+    }
+
+    #[test]
+    fn test_single_page() {
+        let result = parse_query(fixture!("single_page"), &ctx()).unwrap();
+
+        assert_eq!(
+            result.paging,
+            Some(Paging {
+                from: 1,
+                to: 2,
+                total: 2
+            })
+        );
+        assert_eq!(
+            result.bookings.iter().map(summary).collect::<Vec<_>>(),
+            vec![
+                (
+                    "1001",
+                    "Golf (Hauptbahnhof)",
+                    "demo",
+                    // CET
+                    utc("2020-03-01T13:00:00Z"),
+                    utc("2020-03-01T17:00:00Z"),
+                    Some("Einkaufen"),
+                ),
+                (
+                    "1002",
+                    "Zoe (Marktplatz)",
+                    "demo",
+                    // CEST
+                    utc("2020-07-15T08:00:00Z"),
+                    utc("2020-07-15T09:30:00Z"),
+                    Some("Arzt"),
+                ),
+            ]
+        );
+        assert!(result.bookings.iter().all(|b| b.location.is_none()));
+
+        assert_eq!(result.bookings[0].duration.as_deref(), Some("4 h"));
+        assert!(result.bookings[0].flags.is_empty());
+        assert_eq!(result.bookings[0].state, BookingState::Active);
+        assert_eq!(result.bookings[0].booker, None);
+
+        assert_eq!(result.bookings[1].duration.as_deref(), Some("1,5 h"));
+        assert!(result.bookings[1].flags.is_empty());
+    }
+
+    #[test]
+    fn test_flags() {
+        let result = parse_query(fixture!("flags"), &ctx()).unwrap();
+
+        assert_eq!(result.bookings.len(), 2);
+        assert!(result.bookings[0].flags.is_empty());
+        assert_eq!(
+            result.bookings[1].flags,
+            vec!["Schlüssel im Tresor".to_string(), "Tankkarte".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_inactive() {
+        let result = parse_query(fixture!("inactive"), &ctx()).unwrap();
+
+        assert_eq!(result.bookings.len(), 1);
+        assert_eq!(result.bookings[0].state, BookingState::Inactive);
+        assert_eq!(result.bookings[0].duration.as_deref(), Some("2 d 17 h"));
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let result = parse_query(fixture!("cancelled"), &ctx()).unwrap();
+
+        assert_eq!(result.bookings.len(), 1);
+        assert_eq!(result.bookings[0].state, BookingState::Cancelled);
+        // the description doesn't matter
+        assert_eq!(
+            result.bookings[0].description.as_deref(),
+            Some("Urlaub (storniert)")
+        );
+    }
+
+    #[test]
+    fn test_marker_description() {
+        let result = parse_query(fixture!("marker_description"), &ctx()).unwrap();
+
+        assert_eq!(
+            result
+                .bookings
+                .iter()
+                .map(|b| b.description.as_deref())
+                .collect::<Vec<_>>(),
+            vec![
+                Some("Wartungsarbeiten in der Werkstatt"),
+                Some("Fatal error: Passwort falsch notiert, Datenbankfehler"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_pages() {
+        assert!(matches!(
+            parse_query(fixture!("errors", "maintenance"), &ctx()),
+            Err(Error::Maintenance)
+        ));
+        assert!(matches!(
+            parse_query(fixture!("errors", "wrong_password"), &ctx()),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            parse_query(fixture!("errors", "database"), &ctx()),
+            Err(Error::Upstream(message)) if message == "Datenbankfehler"
+        ));
+    }
+
+    #[test]
+    fn test_entry_state() {
+        // the description mentions a cancellation, but the booking is active
+        assert_eq!(
+            parse_entry_state(fixture!("view_entry", "active"), &ctx()).unwrap(),
+            EntryState::Active
+        );
+        assert_eq!(
+            parse_entry_state(fixture!("view_entry", "cancelled"), &ctx()).unwrap(),
+            EntryState::Cancelled
+        );
+        assert_eq!(
+            parse_entry_state(fixture!("view_entry", "missing"), &ctx()).unwrap(),
+            EntryState::Missing
+        );
+    }
+
+    #[test]
+    fn test_resources() {
+        let resources = parse_resources(fixture!("room_list", "rooms"), &ctx()).unwrap();
+
+        assert_eq!(
+            resources,
+            vec![
+                Resource {
+                    id: "7".into(),
+                    name: "Golf (Hauptbahnhof)".into(),
+                    station: Some("Hauptbahnhof, Parkdeck 2".into()),
+                    category: Some("Kompakt".into()),
+                },
+                Resource {
+                    id: "8".into(),
+                    name: "Zoe (Marktplatz)".into(),
+                    station: Some("Marktplatz & Rathaus".into()),
+                    category: None,
+                },
+            ]
+        );
+
+        let resources = parse_resources(fixture!("room_list", "empty"), &ctx()).unwrap();
+        assert!(resources.is_empty());
+
+        assert!(matches!(
+            parse_resources(fixture!("errors", "maintenance"), &ctx()),
+            Err(Error::Maintenance)
+        ));
+    }
+
+    #[test]
+    fn test_details() {
+        let details = parse_details("1001", fixture!("view_entry", "cancelled"), &ctx()).unwrap();
+
+        assert_eq!(details.created, Some(utc("2020-02-27T17:30:00Z")));
+        assert_eq!(details.modified, Some(utc("2020-02-28T08:15:00Z")));
+        assert_eq!(details.notes, None);
+        assert_eq!(details.state, BookingState::Cancelled);
+
+        let details = parse_details("1001", fixture!("view_entry", "active"), &ctx()).unwrap();
+        assert_eq!(details.state, BookingState::Active);
+        assert_eq!(
+            details.booker,
+            Member {
+                id: "max".into(),
+                name: Some("Max Mustermann".into())
+            }
+        );
+
+        assert!(matches!(
+            parse_details("1001", fixture!("view_entry", "invalid_created"), &ctx()),
+            Err(Error::Parse { page: "view_entry", snippet, .. }) if snippet == "31.02.20, 18:30"
+        ));
+    }
+
+    #[test]
+    fn test_edit_form() {
+        let form = parse_edit_form(fixture!("edit_entry", "booking"), &ctx()).unwrap();
+
+        assert_eq!(form.resource, "7");
+        assert_eq!(form.start, utc("2020-03-01T13:00:00Z"));
+        assert_eq!(form.end, utc("2020-03-01T17:00:00Z"));
+        assert_eq!(
+            form.description.as_deref(),
+            Some("Umzug \"Müller & Söhne\"")
+        );
+
+        let field = |name: &str| {
+            form.fields
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(field("id"), Some("1001"));
+        assert_eq!(field("create_by"), Some("erika"));
+        assert_eq!(field("start_day"), Some("1"));
+        assert_eq!(field("fuel_card"), Some("1"));
+        assert_eq!(field("child_seat"), None);
+        assert_eq!(field("mileage"), Some("12345"));
+        assert_eq!(
+            form.fields
+                .iter()
+                .filter(|(n, _)| n == "billing")
+                .collect::<Vec<_>>(),
+            vec![&("billing".to_string(), "private".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_booking_response() {
+        assert_eq!(
+            parse_booking_response(fixture!("edit_entry_handler", "created"), &ctx()).unwrap(),
+            Some("1002".to_string())
+        );
+        assert!(matches!(
+            parse_booking_response(fixture!("edit_entry_handler", "conflict"), &ctx()),
+            Err(Error::Rejected(Rejection::Conflict))
+        ));
+        assert!(matches!(
+            parse_booking_response(fixture!("edit_entry_handler", "in_past"), &ctx()),
+            Err(Error::Rejected(Rejection::InPast))
+        ));
+        assert!(matches!(
+            parse_booking_response(fixture!("edit_entry_handler", "error"), &ctx()),
+            Err(Error::Rejected(Rejection::Other(message)))
+                if message == "Das Ende liegt vor dem Beginn."
+        ));
+    }
+
+    #[test]
+    fn test_booker() {
+        let result = parse_query(fixture!("booker"), &ctx()).unwrap();
+
+        let members: Vec<_> = result
+            .bookings
+            .iter()
+            .map(|b| (b.booker.clone().unwrap(), b.owner.clone()))
+            .collect();
+        assert_eq!(
+            members,
+            vec![
+                (
+                    Member {
+                        id: "demo".into(),
+                        name: None
+                    },
+                    Member {
+                        id: "erika".into(),
+                        name: Some("Erika Müller".into())
+                    }
+                ),
+                (
+                    Member {
+                        id: "max".into(),
+                        name: Some("Max Mustermann".into())
+                    },
+                    Member {
+                        id: "demo".into(),
+                        name: None
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiple_pages() {
+        let first = parse_query(fixture!("multi_page_1"), &ctx()).unwrap();
+        let second = parse_query(fixture!("multi_page_2"), &ctx()).unwrap();
+
+        assert_eq!(
+            first.paging,
+            Some(Paging {
+                from: 1,
+                to: 2,
+                total: 3
+            })
+        );
+        assert_eq!(
+            second.paging,
+            Some(Paging {
+                from: 3,
+                to: 3,
+                total: 3
+            })
+        );
+
+        let ids: Vec<_> = first
+            .bookings
+            .iter()
+            .chain(second.bookings.iter())
+            .map(|b| b.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["2001", "2002", "2003"]);
+    }
+
+    #[test]
+    fn test_escaped_description() {
+        let result = parse_query(fixture!("escaped_description"), &ctx()).unwrap();
+
+        assert_eq!(result.bookings.len(), 1);
+        assert_eq!(
+            result.bookings[0].description.as_deref(),
+            Some("Umzug \"Müller & Söhne\" <2>")
+        );
+    }
+
+    #[test]
+    fn test_empty_description() {
+        let result = parse_query(fixture!("empty_description"), &ctx()).unwrap();
+
+        assert_eq!(result.bookings.len(), 2);
+        assert_eq!(result.bookings[0].description, None);
+        assert_eq!(result.bookings[1].description, None);
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(
+            date("01.03.20, 14:00"),
+            Ok(("", utc("2020-03-01T13:00:00Z")))
+        );
+        assert_eq!(
+            date("01.03.2020, 14:00"),
+            Ok(("", utc("2020-03-01T13:00:00Z")))
+        );
+        assert!(date("01.03.020, 14:00").is_err());
+        assert!(date("31.02.20, 14:00").is_err());
+        assert!(date("01.03.20, 25:00").is_err());
+    }
+
+    #[test]
+    fn test_date_dst() {
+        // doesn't exist, moves forward to 03:30 CEST
+        assert_eq!(
+            date("29.03.20, 02:30"),
+            Ok(("", utc("2020-03-29T01:30:00Z")))
+        );
+        // exists twice, resolves to the first occurrence (CEST)
+        assert_eq!(
+            date("25.10.20, 02:30"),
+            Ok(("", utc("2020-10-25T00:30:00Z")))
+        );
+        // the gap is only 30 minutes, moves forward to 02:45 (+11:00)
+        assert_eq!(
+            super::date("04.10.20, 02:15", chrono_tz::Australia::Lord_Howe),
+            Ok(("", utc("2020-10-03T15:45:00Z")))
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!(parse_int("99999999999").is_err());
+        assert!(parse_usize("999999999999999999999999").is_err());
+        // reported as error, instead of panicking or dropping the paging information
+        assert!(matches!(
+            parse_query(fixture!("overflow"), &ctx()),
+            Err(Error::Parse { page: "search", snippet, .. })
+                if snippet.starts_with("999999999999999999999999<BR>")
+        ));
+    }
+
+    #[test]
+    fn test_english() {
+        // German is preferred, but the page is in English
+        let result = parse_query(fixture!("english"), &ctx()).unwrap();
+
+        assert_eq!(
+            result.paging,
+            Some(Paging {
+                from: 1,
+                to: 1,
+                total: 1
+            })
+        );
+        assert_eq!(result.bookings.len(), 1);
+        assert_eq!(result.bookings[0].description.as_deref(), Some("Shopping"));
+
+        let result = parse_query(fixture!("english_no_results"), &ctx()).unwrap();
+
+        assert_eq!(result.paging, None);
+        assert!(result.bookings.is_empty());
+    }
+
+    #[test]
+    fn test_english_details() {
+        // German is preferred, but the page is in English
+        let body = fixture!("view_entry", "english");
+
+        assert_eq!(
+            parse_entry_state(body, &ctx()).unwrap(),
+            EntryState::Cancelled
+        );
+
+        let details = parse_details("1001", body, &ctx()).unwrap();
+        assert_eq!(details.resource, "Golf (Hauptbahnhof)");
+        assert_eq!(details.station.as_deref(), Some("Hauptbahnhof, Parkdeck 2"));
+        assert_eq!(details.description.as_deref(), Some("Shopping"));
+        assert_eq!(details.start, utc("2020-03-01T13:00:00Z"));
+        assert_eq!(details.owner.id.as_ref(), "demo");
+        assert_eq!(details.state, BookingState::Cancelled);
+    }
+
+    #[test]
+    fn test_multi_day() {
+        let result = parse_query(fixture!("multi_day"), &ctx()).unwrap();
+
+        assert_eq!(result.bookings.len(), 1);
+        let booking = &result.bookings[0];
+        // the booking spans the change to daylight saving time
+        assert_eq!(booking.start, utc("2020-03-27T15:00:00Z"));
+        assert_eq!(booking.end, utc("2020-03-30T08:00:00Z"));
+    }
 }
@@ -0,0 +1,184 @@
+use crate::client::ListOptions;
+use crate::config::User;
+use crate::error::{Error, ErrorInfo, Rejection, Result};
+use crate::transport::{BoxStream, Method, Request, Response, Transport};
+use elkato_common::data::{Booking, BookingChanges, BookingDetails, NewBooking, Resource};
+use futures::{stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::Arc;
+use url::Url;
+
+#[cfg(feature = "reqwest")]
+use crate::transport::ReqwestTransport;
+
+/// A client for the REST API of elkato-proxy.
+///
+/// It offers the same operations as the [`Client`](crate::Client), with the same errors.
+#[derive(Clone, Debug)]
+pub struct ProxyClient {
+    url: Url,
+    transport: Arc<dyn Transport>,
+}
+
+/// Map an error response of the proxy back to the error of the client.
+///
+/// Errors without a kind didn't originate from Elkato, and are mapped by their status code.
+fn error_from_response(resp: Response) -> Error {
+    let message = match serde_json::from_str::<ErrorInfo>(&resp.body) {
+        Ok(ErrorInfo {
+            kind: Some(kind), ..
+        }) => return kind.into(),
+        Ok(info) => info.message,
+        Err(_) => resp.body,
+    };
+
+    match resp.status {
+        400 => Error::InvalidOptions(message),
+        401 | 403 => Error::AuthenticationFailed,
+        404 => Error::NotFound(message),
+        409 => Error::Rejected(Rejection::Other(message)),
+        502 => Error::Upstream(message),
+        503 => Error::Transport(message.into()),
+        status => Error::UpstreamHttp(status),
+    }
+}
+
+impl ProxyClient {
+    /// Create a new client for the proxy at `url`, using `reqwest`.
+    #[cfg(feature = "reqwest")]
+    pub fn new(url: Url) -> Result<Self> {
+        Ok(Self::with_transport(url, ReqwestTransport::new()?))
+    }
+
+    /// Create a new client for the proxy at `url`, sending requests through the provided
+    /// transport.
+    pub fn with_transport<T>(url: Url, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        ProxyClient {
+            url,
+            transport: Arc::new(transport),
+        }
+    }
+
+    /// List the bookings of the user, from a week ago until a week ahead.
+    pub async fn list_current_bookings(&self, user: User) -> Result<Vec<Booking>> {
+        let url = self.url(&[&user.club, "bookings", "current"])?;
+        self.fetch(self.request(Method::Get, url, &user)).await
+    }
+
+    /// List all bookings matching the options.
+    pub fn list_bookings(
+        &self,
+        user: User,
+        options: ListOptions,
+    ) -> BoxStream<'static, Result<Booking>> {
+        let client = self.clone();
+
+        let stream = stream::once(async move {
+            let mut url = client.url(&[&user.club, "bookings"])?;
+            let query = serde_urlencoded::to_string(&options)
+                .map_err(|err| Error::InvalidOptions(err.to_string()))?;
+            url.set_query(Some(&query));
+
+            let bookings: Vec<Booking> = client
+                .fetch(client.request(Method::Get, url, &user))
+                .await?;
+            Result::Ok(stream::iter(bookings.into_iter().map(Ok)))
+        })
+        .try_flatten();
+
+        Box::pin(stream)
+    }
+
+    /// List the resources (cars) of the club.
+    pub async fn list_resources(&self, user: User) -> Result<Vec<Resource>> {
+        let url = self.url(&[&user.club, "resources"])?;
+        self.fetch(self.request(Method::Get, url, &user)).await
+    }
+
+    /// Get all details of a single booking.
+    pub async fn get_booking(&self, user: User, id: &str) -> Result<BookingDetails> {
+        let url = self.url(&[&user.club, "bookings", id])?;
+        self.fetch(self.request(Method::Get, url, &user)).await
+    }
+
+    /// Create a new booking.
+    pub async fn create_booking(&self, user: User, booking: NewBooking) -> Result<Booking> {
+        let url = self.url(&[&user.club, "bookings"])?;
+        let request = self.json_request(Method::Post, url, &user, &booking)?;
+        self.fetch(request).await
+    }
+
+    /// Change an existing booking.
+    pub async fn update_booking(
+        &self,
+        user: User,
+        id: &str,
+        changes: BookingChanges,
+    ) -> Result<()> {
+        let url = self.url(&[&user.club, "bookings", id])?;
+        let request = self.json_request(Method::Put, url, &user, &changes)?;
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Cancel a booking.
+    pub async fn cancel_booking(&self, user: User, id: &str) -> Result<()> {
+        let url = self.url(&[&user.club, "bookings", id])?;
+        self.send(self.request(Method::Delete, url, &user))
+            .await
+            .map(|_| ())
+    }
+
+    /// Create the URL of a resource of the API.
+    fn url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| Error::InvalidOptions(format!("Invalid proxy URL: {}", self.url)))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// Create an authenticated request.
+    fn request(&self, method: Method, url: Url, user: &User) -> Request {
+        let mut request = Request::new(method, url);
+        request.auth = Some((user.username.clone(), user.password.clone()));
+        request
+    }
+
+    /// Create an authenticated request, with a JSON body.
+    fn json_request<T: Serialize>(
+        &self,
+        method: Method,
+        url: Url,
+        user: &User,
+        body: &T,
+    ) -> Result<Request> {
+        let mut request = self.request(method, url, user);
+        request.json = Some(
+            serde_json::to_string(body).map_err(|err| Error::InvalidOptions(err.to_string()))?,
+        );
+        Ok(request)
+    }
+
+    /// Send a request and return the body of the response.
+    async fn send(&self, request: Request) -> Result<String> {
+        let resp = self.transport.send(request).await?;
+
+        match resp.status {
+            200..=299 => Ok(resp.body),
+            _ => Err(error_from_response(resp)),
+        }
+    }
+
+    /// Send a request and decode the JSON body of the response.
+    async fn fetch<T: DeserializeOwned>(&self, request: Request) -> Result<T> {
+        let body = self.send(request).await?;
+
+        serde_json::from_str(&body)
+            .map_err(|err| Error::Upstream(format!("Invalid response: {}", err)))
+    }
+}
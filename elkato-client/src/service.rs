@@ -0,0 +1,184 @@
+use crate::client::{Client, ListOptions};
+use crate::config::User;
+use crate::error::{Error, Result};
+use crate::proxy::ProxyClient;
+use crate::transport::BoxStream;
+use async_trait::async_trait;
+use elkato_common::data::{Booking, BookingDetails, Resource};
+use futures::stream;
+
+/// Read access to the bookings of a club.
+///
+/// Implemented by the [`Client`], talking to Elkato, the [`ProxyClient`], talking to
+/// elkato-proxy, and the [`FakeBookingService`], for testing.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait BookingService: Send + Sync {
+    /// List all bookings matching the options.
+    fn list_bookings(
+        &self,
+        user: User,
+        options: ListOptions,
+    ) -> BoxStream<'static, Result<Booking>>;
+
+    /// Get all details of a single booking.
+    async fn get_booking(&self, user: User, id: &str) -> Result<BookingDetails>;
+
+    /// List the resources (cars) of the club.
+    async fn list_resources(&self, user: User) -> Result<Vec<Resource>>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BookingService for Client {
+    fn list_bookings(
+        &self,
+        user: User,
+        options: ListOptions,
+    ) -> BoxStream<'static, Result<Booking>> {
+        Client::list_bookings(self, user, options)
+    }
+
+    async fn get_booking(&self, user: User, id: &str) -> Result<BookingDetails> {
+        Client::get_booking(self, user, id).await
+    }
+
+    async fn list_resources(&self, user: User) -> Result<Vec<Resource>> {
+        Client::list_resources(self, user).await
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BookingService for ProxyClient {
+    fn list_bookings(
+        &self,
+        user: User,
+        options: ListOptions,
+    ) -> BoxStream<'static, Result<Booking>> {
+        ProxyClient::list_bookings(self, user, options)
+    }
+
+    async fn get_booking(&self, user: User, id: &str) -> Result<BookingDetails> {
+        ProxyClient::get_booking(self, user, id).await
+    }
+
+    async fn list_resources(&self, user: User) -> Result<Vec<Resource>> {
+        ProxyClient::list_resources(self, user).await
+    }
+}
+
+/// A booking service, serving a fixed set of bookings and resources from memory.
+///
+/// Users are not checked. Date filters are applied in UTC.
+#[derive(Clone, Debug, Default)]
+pub struct FakeBookingService {
+    bookings: Vec<Booking>,
+    resources: Vec<Resource>,
+}
+
+impl FakeBookingService {
+    pub fn new(bookings: Vec<Booking>) -> Self {
+        FakeBookingService {
+            bookings,
+            resources: vec![],
+        }
+    }
+
+    pub fn with_resources(mut self, resources: Vec<Resource>) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    fn matches(&self, booking: &Booking, options: &ListOptions) -> bool {
+        if let Some(owner) = &options.owner {
+            if &booking.owner.id != owner {
+                return false;
+            }
+        }
+        if let Some(id) = &options.resource {
+            // bookings only carry the name of the resource
+            let name = self
+                .resources
+                .iter()
+                .find(|r| &r.id == id)
+                .map_or(id.as_ref(), |r| r.name.as_str());
+            if booking.resource != name {
+                return false;
+            }
+        }
+        if let Some(booker) = &options.booker {
+            if booking.booker.as_ref().map(|m| &m.id) != Some(booker) {
+                return false;
+            }
+        }
+
+        let start = booking.start.date();
+        let end = booking.end.date();
+        let in_range = |date, from: Option<_>, to: Option<_>| {
+            from.filter(|from| date < *from).is_none() && to.filter(|to| date > *to).is_none()
+        };
+        if !in_range(start, options.start_from, options.start_to)
+            || !in_range(end, options.end_from, options.end_to)
+        {
+            return false;
+        }
+
+        options.state.includes(booking.state)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl BookingService for FakeBookingService {
+    fn list_bookings(
+        &self,
+        _user: User,
+        options: ListOptions,
+    ) -> BoxStream<'static, Result<Booking>> {
+        let bookings: Vec<_> = self
+            .bookings
+            .iter()
+            .filter(|b| self.matches(b, &options))
+            .cloned()
+            .map(Ok)
+            .collect();
+
+        Box::pin(stream::iter(bookings))
+    }
+
+    async fn get_booking(&self, _user: User, id: &str) -> Result<BookingDetails> {
+        let booking = self
+            .bookings
+            .iter()
+            .find(|b| b.id == id)
+            .ok_or_else(|| Error::NotFound(id.into()))?;
+
+        Ok(BookingDetails {
+            id: booking.id.clone(),
+            resource: booking.resource.clone(),
+            station: self
+                .resources
+                .iter()
+                .find(|r| r.name == booking.resource)
+                .and_then(|r| r.station.clone()),
+            booker: booking
+                .booker
+                .clone()
+                .unwrap_or_else(|| booking.owner.clone()),
+            owner: booking.owner.clone(),
+            start: booking.start,
+            end: booking.end,
+            description: booking.description.clone(),
+            notes: None,
+            created: None,
+            modified: None,
+            location: booking.location.clone(),
+            state: booking.state,
+        })
+    }
+
+    async fn list_resources(&self, _user: User) -> Result<Vec<Resource>> {
+        Ok(self.resources.clone())
+    }
+}
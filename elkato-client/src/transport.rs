@@ -0,0 +1,270 @@
+//! The HTTP transport used to talk to Elkato.
+//!
+//! The [`Client`](crate::Client) only builds requests and parses responses. Sending them is left
+//! to a [`Transport`], so that the same logic can be used with different HTTP stacks, or without
+//! any network at all.
+
+use crate::error::Result;
+use async_trait::async_trait;
+use std::fmt;
+use url::Url;
+
+/// A boxed stream.
+///
+/// It is `Send`, except on WASM, where futures of the browser can't be sent between threads.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxStream<'a, T> = futures::stream::BoxStream<'a, T>;
+/// A boxed stream.
+///
+/// It is `Send`, except on WASM, where futures of the browser can't be sent between threads.
+#[cfg(target_arch = "wasm32")]
+pub type BoxStream<'a, T> = futures::stream::LocalBoxStream<'a, T>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+/// An HTTP request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    pub method: Method,
+    /// The URL, including the query parameters.
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    /// Username and password for basic authentication.
+    pub auth: Option<(String, Option<String>)>,
+    /// Form fields, sent URL encoded as the body.
+    pub form: Vec<(String, String)>,
+    /// A JSON document, sent as the body instead of the form.
+    pub json: Option<String>,
+}
+
+impl Request {
+    pub fn new(method: Method, url: Url) -> Self {
+        Request {
+            method,
+            url,
+            headers: vec![],
+            auth: None,
+            form: vec![],
+            json: None,
+        }
+    }
+
+    /// Get the value of a query parameter.
+    pub fn query(&self, name: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+/// An HTTP response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    /// A successful response, with the provided body.
+    pub fn ok<S: Into<String>>(body: S) -> Self {
+        Response {
+            status: 200,
+            body: body.into(),
+        }
+    }
+
+    /// An empty response, with the provided status code.
+    pub fn status(status: u16) -> Self {
+        Response {
+            status,
+            body: String::new(),
+        }
+    }
+}
+
+/// Sends requests to Elkato.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send a request, and return the response, whatever its status code.
+    async fn send(&self, request: Request) -> Result<Response>;
+}
+
+/// A transport using `reqwest`.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new() -> Result<Self> {
+        Ok(ReqwestTransport {
+            client: reqwest::ClientBuilder::new().build()?,
+        })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: Request) -> Result<Response> {
+        let method = match request.method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Put => reqwest::Method::PUT,
+            Method::Delete => reqwest::Method::DELETE,
+        };
+        let mut builder = self.client.request(method, request.url);
+        builder = match request.json {
+            Some(json) => builder
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(json),
+            None if !request.form.is_empty() => builder.form(&request.form),
+            None => builder,
+        };
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if let Some((username, password)) = request.auth {
+            builder = builder.basic_auth(username, password);
+        }
+
+        let resp = builder.send().await?;
+
+        log::debug!("URL: {}", resp.url());
+
+        Ok(Response {
+            status: resp.status().as_u16(),
+            body: resp.text().await?,
+        })
+    }
+}
+
+/// A transport answering all requests from memory.
+pub struct MemoryTransport {
+    handler: Box<dyn Fn(&Request) -> Response + Send + Sync>,
+}
+
+impl MemoryTransport {
+    /// Create a new transport, answering requests with the provided function.
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        MemoryTransport {
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryTransport").finish()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Transport for MemoryTransport {
+    async fn send(&self, request: Request) -> Result<Response> {
+        Ok((self.handler)(&request))
+    }
+}
+
+/// A transport using the `fetch` API of the browser.
+#[cfg(all(feature = "fetch", target_arch = "wasm32"))]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FetchTransport;
+
+#[cfg(all(feature = "fetch", target_arch = "wasm32"))]
+fn js_error(err: wasm_bindgen::JsValue) -> crate::error::Error {
+    crate::error::Error::Transport(format!("{:?}", err).into())
+}
+
+#[cfg(all(feature = "fetch", target_arch = "wasm32"))]
+#[async_trait(?Send)]
+impl Transport for FetchTransport {
+    async fn send(&self, request: Request) -> Result<Response> {
+        use wasm_bindgen::{JsCast, JsValue};
+        use wasm_bindgen_futures::JsFuture;
+
+        let headers = web_sys::Headers::new().map_err(js_error)?;
+        for (name, value) in &request.headers {
+            headers.set(name, value).map_err(js_error)?;
+        }
+        if let Some((username, password)) = &request.auth {
+            let credentials = format!("{}:{}", username, password.as_deref().unwrap_or_default());
+            headers
+                .set(
+                    "Authorization",
+                    &format!("Basic {}", base64::encode(credentials)),
+                )
+                .map_err(js_error)?;
+        }
+
+        let mut init = web_sys::RequestInit::new();
+        init.method(request.method.as_str());
+        if let Some(json) = &request.json {
+            headers
+                .set("Content-Type", "application/json")
+                .map_err(js_error)?;
+            init.body(Some(&JsValue::from_str(json)));
+        } else if !request.form.is_empty() {
+            let body = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&request.form)
+                .finish();
+            headers
+                .set("Content-Type", "application/x-www-form-urlencoded")
+                .map_err(js_error)?;
+            init.body(Some(&JsValue::from_str(&body)));
+        }
+        init.headers(&headers);
+
+        let req = web_sys::Request::new_with_str_and_init(request.url.as_str(), &init)
+            .map_err(js_error)?;
+
+        let window = web_sys::window().ok_or_else(|| js_error(JsValue::from_str("No window")))?;
+        let resp: web_sys::Response = JsFuture::from(window.fetch_with_request(&req))
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+
+        let body = JsFuture::from(resp.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?;
+
+        Ok(Response {
+            status: resp.status(),
+            body: body.as_string().unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "blocking")]
+
+mod mock;
+
+use elkato_client::blocking::Client;
+use elkato_client::{Config, Error, ListOptions, User};
+use mock::{MockBooking, MockElkato, MockServer};
+
+fn user() -> User {
+    User {
+        club: "demo".into(),
+        username: "demo".into(),
+        password: Some("demo".into()),
+    }
+}
+
+#[test]
+fn test_list() {
+    // the mock server needs a runtime of its own, the blocking client must not run inside it
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.enter(|| {
+        MockServer::start(MockElkato {
+            bookings: vec![
+                MockBooking::new("1", "demo", "2020-03-01T08:00:00Z", "2020-03-01T09:00:00Z"),
+                MockBooking::new("2", "demo", "2020-03-02T08:00:00Z", "2020-03-02T09:00:00Z"),
+                MockBooking::new("3", "demo", "2020-03-03T08:00:00Z", "2020-03-03T09:00:00Z"),
+            ],
+            ..Default::default()
+        })
+    });
+
+    let client = Client::new(Config::new(server.url.clone())).unwrap();
+
+    let ids: Vec<_> = client
+        .list_bookings(user(), ListOptions::default())
+        .map(|b| b.unwrap().id)
+        .collect();
+    assert_eq!(ids, vec!["1", "2", "3"]);
+
+    let mut result = client.list_bookings(
+        User {
+            password: Some("wrong".into()),
+            ..user()
+        },
+        Default::default(),
+    );
+    assert!(matches!(
+        result.next(),
+        Some(Err(Error::AuthenticationFailed))
+    ));
+}
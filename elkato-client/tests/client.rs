@@ -0,0 +1,545 @@
+mod mock;
+
+use chrono::{DateTime, Utc};
+use elkato_client::transport::{MemoryTransport, Response};
+use elkato_client::{BookingState, Client, Config, Cursor, Error, ListOptions, User};
+use elkato_common::data::{Booking, BookingChanges, NewBooking};
+use futures::TryStreamExt;
+use mock::{MockBooking, MockElkato, MockServer};
+use std::sync::{Arc, Mutex};
+
+fn user() -> User {
+    User {
+        club: "demo".into(),
+        username: "demo".into(),
+        password: Some("demo".into()),
+    }
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::new(Config::new(server.url.clone())).unwrap()
+}
+
+async fn list(client: &Client, user: User, options: ListOptions) -> Result<Vec<Booking>, Error> {
+    client.list_bookings(user, options).try_collect().await
+}
+
+fn ids(bookings: &[Booking]) -> Vec<&str> {
+    bookings.iter().map(|b| b.id.as_str()).collect()
+}
+
+fn bookings() -> Vec<MockBooking> {
+    let mut inactive =
+        MockBooking::new("6", "demo", "2020-03-06T08:00:00Z", "2020-03-06T09:00:00Z");
+    inactive.active = false;
+
+    vec![
+        MockBooking::new("1", "demo", "2020-03-01T08:00:00Z", "2020-03-01T09:00:00Z"),
+        MockBooking::new("2", "demo", "2020-03-02T08:00:00Z", "2020-03-02T09:00:00Z"),
+        MockBooking::new("3", "other", "2020-03-03T08:00:00Z", "2020-03-03T09:00:00Z"),
+        MockBooking::new("4", "demo", "2020-03-04T08:00:00Z", "2020-03-04T09:00:00Z"),
+        MockBooking::new("5", "demo", "2020-03-05T08:00:00Z", "2020-03-05T09:00:00Z"),
+        inactive,
+    ]
+}
+
+fn date(s: &str) -> chrono::Date<Utc> {
+    s.parse::<DateTime<Utc>>().unwrap().date()
+}
+
+#[tokio::test]
+async fn test_memory_transport() {
+    let transport = MemoryTransport::new(|request| {
+        assert_eq!(request.url.path(), "/buchung/search.php");
+        assert_eq!(request.query("club").as_deref(), Some("demo"));
+        match request.auth {
+            Some((ref username, _)) if username == "demo" => {
+                Response::ok(include_str!("fixtures/search/single_page.html"))
+            }
+            _ => Response::status(401),
+        }
+    });
+    let client = Client::with_transport(
+        Config::new("https://www.elkato.de".parse().unwrap()),
+        transport,
+    )
+    .unwrap();
+
+    let result = list(&client, user(), Default::default()).await.unwrap();
+    assert_eq!(ids(&result), vec!["1001", "1002"]);
+
+    let result = list(
+        &client,
+        User {
+            username: "other".into(),
+            ..user()
+        },
+        Default::default(),
+    )
+    .await;
+    assert!(matches!(result, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn test_list_paging() {
+    let server = MockServer::start(MockElkato {
+        bookings: bookings(),
+        ..Default::default()
+    });
+    let client = client(&server);
+
+    let result = list(&client, user(), Default::default()).await.unwrap();
+
+    assert_eq!(ids(&result), vec!["1", "2", "3", "4", "5"]);
+    assert_eq!(
+        result[0].location.as_ref().map(|url| url.path()),
+        Some("/buchung/view_entry.php")
+    );
+}
+
+#[tokio::test]
+async fn test_list_page() {
+    let server = MockServer::start(MockElkato {
+        bookings: bookings(),
+        ..Default::default()
+    });
+    let client = client(&server);
+
+    let (first, cursor) = client
+        .list_bookings_page(user(), Default::default(), None)
+        .await
+        .unwrap();
+    assert_eq!(ids(&first), vec!["1", "2"]);
+    assert_eq!(
+        cursor,
+        Some(Cursor {
+            offset: 2,
+            total: Some(5)
+        })
+    );
+
+    // resume from a stored cursor
+    let stored = serde_json::to_string(&cursor).unwrap();
+    let cursor: Option<Cursor> = serde_json::from_str(&stored).unwrap();
+
+    let (second, cursor) = client
+        .list_bookings_page(user(), Default::default(), cursor)
+        .await
+        .unwrap();
+    assert_eq!(ids(&second), vec!["3", "4"]);
+
+    let (last, cursor) = client
+        .list_bookings_page(user(), Default::default(), cursor)
+        .await
+        .unwrap();
+    assert_eq!(ids(&last), vec!["5"]);
+    assert_eq!(cursor, None);
+
+    // past the end
+    let (empty, cursor) = client
+        .list_bookings_page(
+            user(),
+            Default::default(),
+            Some(Cursor {
+                offset: 6,
+                total: Some(5),
+            }),
+        )
+        .await
+        .unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(cursor, None);
+
+    // the results changed since the cursor was created
+    let result = client
+        .list_bookings_page(
+            user(),
+            Default::default(),
+            Some(Cursor {
+                offset: 2,
+                total: Some(7),
+            }),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::Upstream(_))));
+}
+
+#[tokio::test]
+async fn test_list_concurrent() {
+    let server = MockServer::start(MockElkato {
+        bookings: bookings(),
+        page_size: 1,
+        // only answers, if the pages overlap
+        hold: Some((1, 2)),
+        ..Default::default()
+    });
+    let mut config = Config::new(server.url.clone());
+    config.page_concurrency = 3;
+    let client = Client::new(config).unwrap();
+
+    let result = list(&client, user(), Default::default()).await.unwrap();
+
+    assert_eq!(ids(&result), vec!["1", "2", "3", "4", "5"]);
+}
+
+#[tokio::test]
+async fn test_list_changed() {
+    // the second page reports a different total than the first one
+    let transport = MemoryTransport::new(|request| match request.query("search_pos").as_deref() {
+        Some("0") => Response::ok(include_str!("fixtures/search/multi_page_1.html")),
+        _ => Response::ok(include_str!("fixtures/search/single_page.html")),
+    });
+    let client = Client::with_transport(
+        Config::new("https://www.elkato.de".parse().unwrap()),
+        transport,
+    )
+    .unwrap();
+
+    let result = list(&client, user(), Default::default()).await;
+
+    assert!(matches!(result, Err(Error::Upstream(_))));
+}
+
+#[tokio::test]
+async fn test_list_implausible_total() {
+    let transport = MemoryTransport::new(|_| {
+        Response::ok(
+            include_str!("fixtures/search/multi_page_1.html")
+                .replace("von 3", "von 100000000000000000"),
+        )
+    });
+    let client = Client::with_transport(
+        Config::new("https://www.elkato.de".parse().unwrap()),
+        transport,
+    )
+    .unwrap();
+
+    let result = list(&client, user(), Default::default()).await;
+
+    assert!(
+        matches!(result, Err(Error::Parse { page: "search", ref snippet, .. }) if snippet.starts_with("100000000000000000"))
+    );
+}
+
+#[tokio::test]
+async fn test_list_spawned() {
+    let server = MockServer::start(MockElkato {
+        bookings: bookings(),
+        ..Default::default()
+    });
+
+    // the stream must outlive the client
+    let stream = client(&server).list_bookings(user(), Default::default());
+    let result: Vec<Booking> = tokio::spawn(stream.try_collect()).await.unwrap().unwrap();
+
+    assert_eq!(ids(&result), vec!["1", "2", "3", "4", "5"]);
+}
+
+#[tokio::test]
+async fn test_list_filters() {
+    let server = MockServer::start(MockElkato {
+        bookings: bookings(),
+        ..Default::default()
+    });
+    let client = client(&server);
+
+    let result = list(
+        &client,
+        user(),
+        ListOptions {
+            owner: Some("demo".into()),
+            start_from: Some(date("2020-03-02T00:00:00Z")),
+            end_to: Some(date("2020-03-04T00:00:00Z")),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(ids(&result), vec!["2", "4"]);
+}
+
+#[tokio::test]
+async fn test_list_state() {
+    let server = MockServer::start(MockElkato {
+        bookings: bookings(),
+        ..Default::default()
+    });
+    let client = client(&server);
+
+    let inactive = list(
+        &client,
+        user(),
+        ListOptions {
+            state: BookingState::Inactive,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(ids(&inactive), vec!["6"]);
+    assert_eq!(inactive[0].state, BookingState::Inactive);
+
+    let all = list(
+        &client,
+        user(),
+        ListOptions {
+            state: BookingState::All,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(ids(&all), vec!["1", "2", "3", "4", "5", "6"]);
+    assert_eq!(all[0].state, BookingState::Active);
+}
+
+#[tokio::test]
+async fn test_list_empty() {
+    let server = MockServer::start(Default::default());
+    let client = client(&server);
+
+    let result = list(&client, user(), Default::default()).await.unwrap();
+
+    assert!(result.is_empty());
+}
+
+#[tokio::test]
+async fn test_wrong_password() {
+    let server = MockServer::start(Default::default());
+    let client = client(&server);
+
+    let result = list(
+        &client,
+        User {
+            password: Some("wrong".into()),
+            ..user()
+        },
+        Default::default(),
+    )
+    .await;
+
+    assert!(matches!(result, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn test_get_booking() {
+    let mut booking = MockBooking::new("1", "demo", "2020-03-01T08:00:00Z", "2020-03-01T09:00:00Z");
+    booking.description = Some("Einkaufen & mehr".into());
+    let server = MockServer::start(MockElkato {
+        bookings: vec![booking],
+        ..Default::default()
+    });
+    let client = client(&server);
+
+    let details = client.get_booking(user(), "1").await.unwrap();
+    assert_eq!(
+        details.start,
+        "2020-03-01T08:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    assert_eq!(details.owner.id.as_ref(), "demo");
+    assert_eq!(details.booker.id.as_ref(), "demo");
+    assert_eq!(details.description.as_deref(), Some("Einkaufen & mehr"));
+
+    let missing = client.get_booking(user(), "2").await;
+    assert!(matches!(missing, Err(Error::NotFound(_))));
+}
+
+/// A client answering booking submissions with `response`, and searches with `search`.
+fn booking_client(response: &'static str, search: &'static str) -> Client {
+    let transport = MemoryTransport::new(move |request| match request.url.path() {
+        "/buchung/edit_entry_handler.php" => Response::ok(response),
+        "/buchung/search.php" => {
+            assert_eq!(request.query("sel_room").as_deref(), Some("7"));
+            assert_eq!(request.query("sel_owner").as_deref(), Some("demo"));
+            Response::ok(search)
+        }
+        path => panic!("Unexpected request: {}", path),
+    });
+    Client::with_transport(
+        Config::new("https://www.elkato.de".parse().unwrap()),
+        transport,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_create_booking() {
+    let client = booking_client(
+        "<HTML>\n<BODY>\n</BODY>\n</HTML>\n",
+        include_str!("fixtures/search/single_page.html"),
+    );
+
+    // seconds and surrounding whitespace are dropped by Elkato
+    let booking = client
+        .create_booking(
+            user(),
+            NewBooking {
+                resource: "7".into(),
+                start: "2020-03-01T13:00:30Z".parse().unwrap(),
+                end: "2020-03-01T17:00:00.5Z".parse().unwrap(),
+                description: Some(" Einkaufen\n".into()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(booking.id, "1001");
+
+    let client = booking_client(
+        "<HTML>\n<BODY>\n</BODY>\n</HTML>\n",
+        include_str!("fixtures/search/empty_description.html"),
+    );
+
+    let booking = client
+        .create_booking(
+            user(),
+            NewBooking {
+                resource: "7".into(),
+                start: "2020-03-07T08:00:00Z".parse().unwrap(),
+                end: "2020-03-07T11:00:00Z".parse().unwrap(),
+                description: Some("".into()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(booking.id, "4001");
+}
+
+#[tokio::test]
+async fn test_create_booking_id() {
+    // the result page links to the new entry, which wins over matching the values
+    let client = booking_client(
+        include_str!("fixtures/edit_entry_handler/created.html"),
+        include_str!("fixtures/search/single_page.html"),
+    );
+
+    let booking = client
+        .create_booking(
+            user(),
+            NewBooking {
+                resource: "7".into(),
+                start: "2020-07-15T08:00:00Z".parse().unwrap(),
+                end: "2020-07-15T09:30:00Z".parse().unwrap(),
+                description: Some("Arztbesuch".into()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(booking.id, "1002");
+}
+
+/// Update booking 1001, returning the submitted form.
+async fn update(changes: BookingChanges) -> Vec<(String, String)> {
+    let submitted = Arc::new(Mutex::new(vec![]));
+    let form = submitted.clone();
+    let transport = MemoryTransport::new(move |request| match request.url.path() {
+        "/buchung/edit_entry.php" => {
+            assert_eq!(request.query("id").as_deref(), Some("1001"));
+            Response::ok(include_str!("fixtures/edit_entry/booking.html"))
+        }
+        "/buchung/edit_entry_handler.php" => {
+            *form.lock().unwrap() = request.form.clone();
+            Response::ok("<HTML>\n<BODY>\n</BODY>\n</HTML>\n")
+        }
+        path => panic!("Unexpected request: {}", path),
+    });
+    let client = Client::with_transport(
+        Config::new("https://www.elkato.de".parse().unwrap()),
+        transport,
+    )
+    .unwrap();
+
+    client
+        .update_booking(user(), "1001", changes)
+        .await
+        .unwrap();
+
+    let form = submitted.lock().unwrap().clone();
+    form
+}
+
+fn field<'a>(form: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    form.iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[tokio::test]
+async fn test_update_booking() {
+    // extend by two hours, which is 20:00 local time
+    let form = update(BookingChanges {
+        end: Some("2020-03-01T19:00:00Z".parse().unwrap()),
+        ..Default::default()
+    })
+    .await;
+
+    assert_eq!(field(&form, "end_hour"), Some("20"));
+    assert_eq!(field(&form, "start_hour"), Some("14"));
+    // other fields are sent back as they are
+    assert_eq!(field(&form, "create_by"), Some("erika"));
+    assert_eq!(field(&form, "fuel_card"), Some("1"));
+    assert_eq!(field(&form, "billing"), Some("private"));
+    assert_eq!(field(&form, "mileage"), Some("12345"));
+    assert_eq!(field(&form, "id"), Some("1001"));
+    // unchecked checkboxes are not sent, like a browser does
+    assert_eq!(field(&form, "child_seat"), None);
+    assert_eq!(form.iter().filter(|(n, _)| n == "billing").count(), 1);
+    assert_eq!(
+        field(&form, "description"),
+        Some("Umzug \"Müller & Söhne\"")
+    );
+    assert_eq!(form.iter().filter(|(n, _)| n == "end_hour").count(), 1);
+
+    // the description can be removed
+    let form = update(BookingChanges {
+        description: Some(None),
+        ..Default::default()
+    })
+    .await;
+
+    assert_eq!(field(&form, "description"), Some(""));
+}
+
+#[tokio::test]
+async fn test_find_free() {
+    let transport = MemoryTransport::new(|request| match request.url.path() {
+        "/buchung/room_list.php" => Response::ok(include_str!("fixtures/room_list/rooms.html")),
+        "/buchung/search.php" => match request.query("sel_room").as_deref() {
+            Some("7") => Response::ok(include_str!("fixtures/search/single_page.html")),
+            Some("8") => Response::ok(include_str!("fixtures/search/no_results.html")),
+            room => panic!("Unexpected resource: {:?}", room),
+        },
+        path => panic!("Unexpected request: {}", path),
+    });
+    let client = Client::with_transport(
+        Config::new("https://www.elkato.de".parse().unwrap()),
+        transport,
+    )
+    .unwrap();
+
+    let free = client
+        .find_free(
+            user(),
+            "2020-03-01T14:00:00Z".parse().unwrap(),
+            "2020-03-01T15:00:00Z".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = free.iter().map(|r| r.id.as_ref()).collect();
+    assert_eq!(ids, vec!["8"]);
+
+    // empty, and reversed ranges
+    let start: DateTime<Utc> = "2020-03-01T14:00:00Z".parse().unwrap();
+    let result = client.find_free(user(), start, start).await;
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+
+    let result = client
+        .availability(
+            user(),
+            "7".into(),
+            start..start - chrono::Duration::hours(1),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::InvalidOptions(_))));
+}
@@ -0,0 +1,356 @@
+//! A local server, imitating the Elkato pages used by the client.
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Europe::Berlin;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
+use url::Url;
+
+#[derive(Clone, Debug)]
+pub struct MockBooking {
+    pub id: String,
+    pub resource: String,
+    pub user: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub description: Option<String>,
+    pub active: bool,
+}
+
+impl MockBooking {
+    pub fn new(id: &str, user: &str, start: &str, end: &str) -> Self {
+        MockBooking {
+            id: id.into(),
+            resource: "Golf (Hauptbahnhof)".into(),
+            user: user.into(),
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            description: None,
+            active: true,
+        }
+    }
+}
+
+/// The state of the mock server.
+#[derive(Clone, Debug)]
+pub struct MockElkato {
+    pub club: String,
+    pub username: String,
+    pub password: String,
+    pub page_size: usize,
+    pub bookings: Vec<MockBooking>,
+    /// Hold back the search page at the first offset, until the page at the second offset
+    /// was requested.
+    pub hold: Option<(usize, usize)>,
+}
+
+impl Default for MockElkato {
+    fn default() -> Self {
+        MockElkato {
+            club: "demo".into(),
+            username: "demo".into(),
+            password: "demo".into(),
+            page_size: 2,
+            bookings: vec![],
+            hold: None,
+        }
+    }
+}
+
+pub struct MockServer {
+    pub url: Url,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Start the server on a random local port.
+    pub fn start(state: MockElkato) -> Self {
+        let state = Arc::new(Shared {
+            state,
+            requested: Mutex::new(HashSet::new()),
+            notify: Notify::new(),
+        });
+
+        let make = make_service_fn(move |_| {
+            let state = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(state.handle(req).await) }
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make);
+        let url = format!("http://{}", server.local_addr()).parse().unwrap();
+
+        let (tx, rx) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            rx.await.ok();
+        }));
+
+        MockServer {
+            url,
+            shutdown: Some(tx),
+        }
+    }
+}
+
+/// The state of a running server.
+struct Shared {
+    state: MockElkato,
+    /// The offsets of all requested search pages.
+    requested: Mutex<HashSet<usize>>,
+    notify: Notify,
+}
+
+impl Shared {
+    async fn handle(&self, req: Request<Body>) -> Response<Body> {
+        if let Some(offset) = search_offset(&req) {
+            self.requested.lock().unwrap().insert(offset);
+            self.notify.notify();
+
+            if let Some((held, until)) = self.state.hold {
+                let wait = tokio::time::timeout(Duration::from_secs(5), self.requested(until));
+                if offset == held && wait.await.is_err() {
+                    return response(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        format!("Page at offset {} was never requested", until),
+                    );
+                }
+            }
+        }
+
+        self.state.handle(req)
+    }
+
+    /// Wait until the search page at `offset` was requested.
+    async fn requested(&self, offset: usize) {
+        while !self.requested.lock().unwrap().contains(&offset) {
+            self.notify.notified().await;
+        }
+    }
+}
+
+fn search_offset(req: &Request<Body>) -> Option<usize> {
+    if req.uri().path() != "/buchung/search.php" {
+        return None;
+    }
+    url::form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+        .find(|(name, _)| name == "search_pos")
+        .and_then(|(_, pos)| pos.parse().ok())
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+fn response(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/html")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.with_timezone(&Berlin)
+        .format("%d.%m.%y, %H:%M")
+        .to_string()
+}
+
+fn escape(value: &Option<String>) -> String {
+    value
+        .as_ref()
+        .map(|s| htmlescape::encode_minimal(s))
+        .unwrap_or_default()
+}
+
+/// Evaluate a date filter, like `s_from=1&s_from_day=1&s_from_month=3&s_from_year=2020`.
+fn date_filter(query: &HashMap<String, String>, prefix: &str) -> Option<NaiveDate> {
+    if query.get(prefix).map(String::as_str) != Some("1") {
+        return None;
+    }
+    let value = |name: &str| -> i64 {
+        query
+            .get(&format!("{}_{}", prefix, name))
+            .and_then(|v| v.parse().ok())
+            .unwrap()
+    };
+    NaiveDate::from_ymd_opt(
+        value("year") as i32,
+        value("month") as u32,
+        value("day") as u32,
+    )
+}
+
+fn local_date(date: &DateTime<Utc>) -> NaiveDate {
+    let date = date.with_timezone(&Berlin);
+    NaiveDate::from_ymd_opt(date.year(), date.month(), date.day()).unwrap()
+}
+
+impl MockElkato {
+    fn handle(&self, req: Request<Body>) -> Response<Body> {
+        if !self.authenticated(&req) {
+            return response(StatusCode::UNAUTHORIZED, "Unauthorized".into());
+        }
+
+        let query: HashMap<String, String> =
+            url::form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+                .into_owned()
+                .collect();
+
+        if query.get("club") != Some(&self.club) {
+            return response(StatusCode::NOT_FOUND, "Unknown club".into());
+        }
+
+        match req.uri().path() {
+            "/buchung/search.php" => self.search(&query),
+            "/buchung/view_entry.php" => self.view_entry(&query),
+            _ => response(StatusCode::NOT_FOUND, "Not found".into()),
+        }
+    }
+
+    fn authenticated(&self, req: &Request<Body>) -> bool {
+        let expected = format!(
+            "Basic {}",
+            base64::encode(format!("{}:{}", self.username, self.password))
+        );
+        req.headers()
+            .get("Authorization")
+            .map(|value| value.as_bytes() == expected.as_bytes())
+            .unwrap_or_default()
+    }
+
+    fn search(&self, query: &HashMap<String, String>) -> Response<Body> {
+        let offset: usize = match query.get("search_pos").and_then(|pos| pos.parse().ok()) {
+            Some(offset) => offset,
+            None => return response(StatusCode::BAD_REQUEST, "Missing search_pos".into()),
+        };
+
+        let active = query.contains_key("active");
+        let inactive = query.contains_key("inactive");
+        let owner = query.get("sel_owner").filter(|o| o.as_str() != "all");
+        let room = query.get("sel_room").filter(|r| r.as_str() != "all");
+
+        let s_from = date_filter(query, "s_from");
+        let s_to = date_filter(query, "s_to");
+        let e_from = date_filter(query, "e_from");
+        let e_to = date_filter(query, "e_to");
+
+        let matches: Vec<_> = self
+            .bookings
+            .iter()
+            .filter(|b| (b.active && active) || (!b.active && inactive))
+            .filter(|b| owner.map(|o| &b.user == o).unwrap_or(true))
+            .filter(|b| room.map(|r| &b.resource == r).unwrap_or(true))
+            .filter(|b| s_from.map(|d| local_date(&b.start) >= d).unwrap_or(true))
+            .filter(|b| s_to.map(|d| local_date(&b.start) <= d).unwrap_or(true))
+            .filter(|b| e_from.map(|d| local_date(&b.end) >= d).unwrap_or(true))
+            .filter(|b| e_to.map(|d| local_date(&b.end) <= d).unwrap_or(true))
+            .collect();
+
+        let mut body = String::from(
+            "<HTML>\n<HEAD>\n<TITLE>Elkato - Suche</TITLE>\n</HEAD>\n<BODY>\n<H3>Suchergebnisse</H3>\n",
+        );
+
+        if matches.is_empty() {
+            body.push_str("<B>Die Suche ergab keine Treffer!</B>\n</BODY>\n</HTML>\n");
+            return response(StatusCode::OK, body);
+        }
+
+        let page: Vec<_> = matches.iter().skip(offset).take(self.page_size).collect();
+
+        body.push_str(&format!(
+            "<B>Eintr&auml;ge {} bis {} von {}<BR></B>\n",
+            offset + 1,
+            offset + page.len(),
+            matches.len()
+        ));
+        body.push_str("<TABLE border=1>\n<TR>\n<TH>ID</TH><TH>Fahrzeug</TH><TH>Nutzer</TH><TH colspan=2>Beginn</TH><TH colspan=2>Ende</TH><TH>Dauer</TH><TH>Details</TH><TH>Beschreibung</TH>\n</TR>\n");
+
+        for b in page {
+            let style = if b.active {
+                "background-color: #ffffff"
+            } else {
+                "background-color: #cccccc"
+            };
+            body.push_str(&format!(
+                r#"<TR >
+  <TD align=right>{id}</TD>
+  <TD nowrap>{resource}</TD>
+  <TD nowrap>{user}</TD>
+  <TD style="{style}">-</TD>
+  <TD nowrap style="{style}">{start}</TD>
+  <TD style="{style}">-</TD>
+  <TD nowrap style="{style}">{end}</TD>
+  <TD>{duration} min</TD>
+  <TD><A href="view_entry.php?club=demo&amp;id={id}">Details</A></TD>
+  <TD nowrap>{description}</TD>
+</TR>
+"#,
+                id = b.id,
+                resource = b.resource,
+                user = b.user,
+                style = style,
+                start = format_date(&b.start),
+                end = format_date(&b.end),
+                duration = (b.end - b.start).num_minutes(),
+                description = escape(&b.description),
+            ));
+        }
+
+        body.push_str("</TABLE>\n</BODY>\n</HTML>\n");
+
+        response(StatusCode::OK, body)
+    }
+
+    fn view_entry(&self, query: &HashMap<String, String>) -> Response<Body> {
+        let booking = match query
+            .get("id")
+            .and_then(|id| self.bookings.iter().find(|b| &b.id == id))
+        {
+            Some(booking) => booking,
+            None => {
+                return response(
+                    StatusCode::OK,
+                    "<HTML>\n<BODY>\n<H2>Ung&uuml;ltige Eintrags-ID</H2>\n</BODY>\n</HTML>\n"
+                        .into(),
+                )
+            }
+        };
+
+        let rows = vec![
+            ("Beschreibung", escape(&booking.description)),
+            ("Fahrzeug", booking.resource.clone()),
+            ("Beginn", format_date(&booking.start)),
+            ("Ende", format_date(&booking.end)),
+            ("Gebucht von", booking.user.clone()),
+            ("Gebucht f&uuml;r", booking.user.clone()),
+            (
+                "Status",
+                if booking.active { "aktiv" } else { "storniert" }.into(),
+            ),
+        ];
+
+        let mut body = String::from("<HTML>\n<BODY>\n<TABLE>\n");
+        for (label, value) in rows {
+            body.push_str(&format!(
+                "<TR>\n<TD><B>{}:</B></TD>\n<TD>{}</TD>\n</TR>\n",
+                label, value
+            ));
+        }
+        body.push_str("</TABLE>\n</BODY>\n</HTML>\n");
+
+        response(StatusCode::OK, body)
+    }
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use elkato_client::transport::{MemoryTransport, Method, Response};
+use elkato_client::{BookingState, Error, ErrorInfo, ListOptions, ProxyClient, Rejection, User};
+use elkato_common::data::{BookingChanges, NewBooking};
+use futures::TryStreamExt;
+
+fn user() -> User {
+    User {
+        club: "demo".into(),
+        username: "demo".into(),
+        password: Some("demo".into()),
+    }
+}
+
+fn proxy(transport: MemoryTransport) -> ProxyClient {
+    ProxyClient::with_transport("https://proxy.example.com/api/".parse().unwrap(), transport)
+}
+
+/// A proxy, responding with the error like elkato-proxy does.
+fn failing(status: u16, err: Error) -> ProxyClient {
+    let body = serde_json::to_string(&ErrorInfo::from(&err)).unwrap();
+    proxy(MemoryTransport::new(move |_| Response {
+        status,
+        body: body.clone(),
+    }))
+}
+
+#[tokio::test]
+async fn test_list_current() {
+    let client = proxy(MemoryTransport::new(|request| {
+        assert_eq!(request.url.path(), "/api/demo/bookings/current");
+        assert_eq!(
+            request.auth,
+            Some(("demo".to_string(), Some("demo".to_string())))
+        );
+        Response::ok(
+            r#"[{
+                "id": "1001",
+                "resource": "Golf (Hauptbahnhof)",
+                "owner": {"id": "demo"},
+                "start": "2020-03-01T13:00:00Z",
+                "end": "2020-03-01T17:00:00Z",
+                "state": "active"
+            }]"#,
+        )
+    }));
+
+    let bookings = client.list_current_bookings(user()).await.unwrap();
+
+    assert_eq!(bookings.len(), 1);
+    assert_eq!(bookings[0].id, "1001");
+    assert_eq!(bookings[0].owner.id.as_ref(), "demo");
+}
+
+#[tokio::test]
+async fn test_errors() {
+    let client = proxy(MemoryTransport::new(|_| Response {
+        status: 401,
+        body: r#"{"message": "Authentication failed"}"#.into(),
+    }));
+    let result = client.list_current_bookings(user()).await;
+    assert!(matches!(result, Err(Error::AuthenticationFailed)));
+
+    let client = failing(502, Error::Upstream("Datenbankfehler".into()));
+    let result = client.list_current_bookings(user()).await;
+    assert!(matches!(result, Err(Error::Upstream(message)) if message == "Datenbankfehler"));
+
+    let client = failing(409, Error::Rejected(Rejection::Other("Gesperrt".into())));
+    let result = client.list_current_bookings(user()).await;
+    assert!(
+        matches!(result, Err(Error::Rejected(Rejection::Other(message))) if message == "Gesperrt")
+    );
+
+    // both map to 502 and 503, the kind tells them apart
+    let client = failing(503, Error::Maintenance);
+    let result = client.list_current_bookings(user()).await;
+    assert!(matches!(result, Err(Error::Maintenance)));
+
+    let client = failing(502, Error::UpstreamHttp(500));
+    let result = client.list_current_bookings(user()).await;
+    assert!(matches!(result, Err(Error::UpstreamHttp(500))));
+
+    let client = failing(
+        502,
+        Error::Parse {
+            page: "search",
+            offset: 12,
+            snippet: "<TR>".into(),
+        },
+    );
+    let result = client.list_current_bookings(user()).await;
+    assert!(matches!(
+        result,
+        Err(Error::Parse {
+            page: "search",
+            offset: 12,
+            ..
+        })
+    ));
+
+    // errors of the proxy itself have no kind
+    let client = proxy(MemoryTransport::new(|_| Response {
+        status: 400,
+        body: r#"{"message": "Invalid query"}"#.into(),
+    }));
+    let result = client.list_current_bookings(user()).await;
+    assert!(matches!(result, Err(Error::InvalidOptions(message)) if message == "Invalid query"));
+}
+
+#[tokio::test]
+async fn test_list() {
+    let client = proxy(MemoryTransport::new(|request| {
+        assert_eq!(request.url.path(), "/api/demo/bookings");
+        assert_eq!(request.query("start_from").as_deref(), Some("2020-03-01"));
+        assert_eq!(request.query("state").as_deref(), Some("all"));
+        assert_eq!(request.query("end_to"), None);
+
+        // the proxy reads the options back from the query
+        let options: ListOptions =
+            serde_urlencoded::from_str(request.url.query().unwrap()).unwrap();
+        assert_eq!(options.state, BookingState::All);
+        Response::ok("[]")
+    }));
+
+    let options = ListOptions {
+        start_from: Some(
+            "2020-03-01T00:00:00Z"
+                .parse::<DateTime<Utc>>()
+                .unwrap()
+                .date(),
+        ),
+        state: BookingState::All,
+        ..Default::default()
+    };
+    let bookings: Vec<_> = client
+        .list_bookings(user(), options)
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert!(bookings.is_empty());
+}
+
+#[tokio::test]
+async fn test_create_and_cancel() {
+    let client = proxy(MemoryTransport::new(|request| {
+        match (request.method, request.url.path()) {
+            (Method::Post, "/api/demo/bookings") => {
+                let json = request.json.as_deref().unwrap();
+                assert!(json.contains(r#""resource":"7""#));
+                Response {
+                    status: 409,
+                    body: r#"{
+                        "message": "Booking conflicts with an existing booking",
+                        "kind": "rejected",
+                        "rejection": "conflict"
+                    }"#
+                    .into(),
+                }
+            }
+            (Method::Delete, "/api/demo/bookings/1001") => Response::status(204),
+            _ => Response::status(404),
+        }
+    }));
+
+    let result = client
+        .create_booking(
+            user(),
+            NewBooking {
+                resource: "7".into(),
+                start: "2020-03-01T13:00:00Z".parse().unwrap(),
+                end: "2020-03-01T17:00:00Z".parse().unwrap(),
+                description: None,
+            },
+        )
+        .await;
+    assert!(matches!(result, Err(Error::Rejected(Rejection::Conflict))));
+
+    client.cancel_booking(user(), "1001").await.unwrap();
+
+    let result = client.cancel_booking(user(), "1002").await;
+    assert!(matches!(result, Err(Error::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_update() {
+    let client = proxy(MemoryTransport::new(|request| {
+        assert_eq!(request.method, Method::Put);
+        assert_eq!(request.url.path(), "/api/demo/bookings/1001");
+
+        // null removes the description, a missing value keeps it
+        let changes: BookingChanges =
+            serde_json::from_str(request.json.as_deref().unwrap()).unwrap();
+        assert_eq!(changes.description, Some(None));
+        assert_eq!(changes.start, None);
+
+        Response::status(204)
+    }));
+
+    client
+        .update_booking(
+            user(),
+            "1001",
+            BookingChanges {
+                description: Some(None),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+}
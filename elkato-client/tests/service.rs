@@ -0,0 +1,130 @@
+use elkato_client::{
+    BookingService, BookingState, Client, Error, FakeBookingService, ListOptions, ProxyClient, User,
+};
+use elkato_common::data::{Booking, Member, Resource};
+use futures::TryStreamExt;
+
+fn user() -> User {
+    User {
+        club: "demo".into(),
+        username: "demo".into(),
+        password: Some("demo".into()),
+    }
+}
+
+fn booking(id: &str, owner: &str, start: &str, end: &str) -> Booking {
+    Booking {
+        id: id.into(),
+        resource: "Golf".into(),
+        owner: Member {
+            id: owner.into(),
+            name: None,
+        },
+        booker: None,
+        start: start.parse().unwrap(),
+        end: end.parse().unwrap(),
+        description: None,
+        location: None,
+        duration: None,
+        flags: vec![],
+        state: BookingState::Active,
+    }
+}
+
+fn service() -> FakeBookingService {
+    let mut cancelled = booking("3", "demo", "2020-03-03T08:00:00Z", "2020-03-03T09:00:00Z");
+    cancelled.state = BookingState::Cancelled;
+
+    FakeBookingService::new(vec![
+        booking("1", "demo", "2020-03-01T08:00:00Z", "2020-03-01T09:00:00Z"),
+        booking("2", "other", "2020-03-02T08:00:00Z", "2020-03-02T09:00:00Z"),
+        cancelled,
+    ])
+    .with_resources(vec![Resource {
+        id: "7".into(),
+        name: "Golf".into(),
+        station: Some("Hauptbahnhof".into()),
+        category: None,
+    }])
+}
+
+/// Code using the trait, without knowing the implementation.
+async fn list(service: &dyn BookingService, options: ListOptions) -> Vec<String> {
+    service
+        .list_bookings(user(), options)
+        .map_ok(|b| b.id)
+        .try_collect()
+        .await
+        .unwrap()
+}
+
+#[test]
+fn test_implementations() {
+    fn service<T: BookingService>() {}
+
+    service::<Client>();
+    service::<ProxyClient>();
+    service::<FakeBookingService>();
+}
+
+#[tokio::test]
+async fn test_fake_list() {
+    let service = service();
+
+    assert_eq!(list(&service, Default::default()).await, vec!["1", "2"]);
+    assert_eq!(
+        list(
+            &service,
+            ListOptions {
+                owner: Some("demo".into()),
+                state: BookingState::All,
+                ..Default::default()
+            }
+        )
+        .await,
+        vec!["1", "3"]
+    );
+    assert_eq!(
+        list(
+            &service,
+            ListOptions {
+                state: BookingState::Inactive,
+                ..Default::default()
+            }
+        )
+        .await,
+        vec!["3"]
+    );
+    assert_eq!(
+        list(
+            &service,
+            ListOptions {
+                resource: Some("7".into()),
+                start_from: Some(
+                    "2020-03-02T00:00:00Z"
+                        .parse::<chrono::DateTime<chrono::Utc>>()
+                        .unwrap()
+                        .date()
+                ),
+                ..Default::default()
+            }
+        )
+        .await,
+        vec!["2"]
+    );
+}
+
+#[tokio::test]
+async fn test_fake_details() {
+    let service = service();
+
+    let details = service.get_booking(user(), "1").await.unwrap();
+    assert_eq!(details.owner.id.as_ref(), "demo");
+    assert_eq!(details.station.as_deref(), Some("Hauptbahnhof"));
+    assert_eq!(details.state, BookingState::Active);
+
+    let missing = service.get_booking(user(), "4").await;
+    assert!(matches!(missing, Err(Error::NotFound(_))));
+
+    assert_eq!(service.list_resources(user()).await.unwrap().len(), 1);
+}
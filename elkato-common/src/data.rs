@@ -1,23 +1,387 @@
 use chrono::DateTime;
 use chrono::Utc;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::ops::Range;
 use url::Url;
 
+/// The id of a resource.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ResourceId(pub String);
+
+/// The id of a club member, which is the name the member logs in with.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MemberId(pub String);
+
+macro_rules! string_id {
+    ($name:ident) => {
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                Self(value.to_string())
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+    };
+}
+
+string_id!(ResourceId);
+string_id!(MemberId);
+
+/// A reference to a club member.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub id: MemberId,
+    /// The display name, if Elkato shows one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => f.write_str(name),
+            None => self.id.fmt(f),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Booking {
     pub id: String,
     pub resource: String,
-    pub user: String,
+    /// The member the booking was made for.
+    pub owner: Member,
+    /// The member who made the booking, if the search results show it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub booker: Option<Member>,
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub location: Option<Url>,
+    /// The duration, as reported by Elkato.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    /// Flags shown in the details column.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    /// Whether the booking is active, inactive, or cancelled. Never `All`.
+    #[serde(default)]
+    pub state: BookingState,
+}
+
+/// The state of a booking, or which bookings to list.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookingState {
+    #[default]
+    Active,
+    /// Inactive, including cancelled bookings when listing.
+    Inactive,
+    /// Cancelled, which is one way of being inactive.
+    Cancelled,
+    /// Both active and inactive bookings, only used for listing.
+    All,
+}
+
+impl BookingState {
+    /// Check if a booking in `state` is listed, when listing bookings in this state.
+    pub fn includes(self, state: BookingState) -> bool {
+        match self {
+            BookingState::All => true,
+            BookingState::Inactive => state != BookingState::Active,
+            _ => self == state,
+        }
+    }
 }
 
 impl Booking {
     pub fn is_active(&self, now: &DateTime<Utc>) -> bool {
         now >= &self.start && now <= &self.end
     }
+
+    /// Check if the booking overlaps with the provided time range.
+    pub fn overlaps(&self, range: &Range<DateTime<Utc>>) -> bool {
+        self.start < range.end && self.end > range.start
+    }
+}
+
+/// A time interval.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Interval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Busy and free times of a resource, within a time range.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Availability {
+    pub busy: Vec<Interval>,
+    pub free: Vec<Interval>,
+}
+
+impl Availability {
+    /// Evaluate the availability inside `range`, from the bookings of a single resource.
+    pub fn from_bookings<'a, I>(range: &Range<DateTime<Utc>>, bookings: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Booking>,
+    {
+        let mut bookings: Vec<_> = bookings
+            .into_iter()
+            .filter(|b| b.overlaps(range))
+            .map(|b| Interval {
+                start: b.start.max(range.start),
+                end: b.end.min(range.end),
+            })
+            .collect();
+        bookings.sort_by_key(|i| i.start);
+
+        // merge overlapping and adjacent bookings
+
+        let mut busy: Vec<Interval> = Vec::new();
+        for i in bookings {
+            match busy.last_mut() {
+                Some(last) if i.start <= last.end => last.end = last.end.max(i.end),
+                _ => busy.push(i),
+            }
+        }
+
+        // the gaps are free
+
+        let mut free = Vec::new();
+        let mut start = range.start;
+        for i in &busy {
+            if i.start > start {
+                free.push(Interval {
+                    start,
+                    end: i.start,
+                });
+            }
+            start = i.end;
+        }
+        if start < range.end {
+            free.push(Interval {
+                start,
+                end: range.end,
+            });
+        }
+
+        Availability { busy, free }
+    }
+
+    /// Check if the resource is free for the whole range.
+    pub fn is_free(&self) -> bool {
+        self.busy.is_empty()
+    }
+}
+
+/// A bookable resource, like a car.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Resource {
+    pub id: ResourceId,
+    pub name: String,
+    /// The station the resource is located at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub station: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+/// All information of a single booking, as shown on its detail page.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BookingDetails {
+    pub id: String,
+    pub resource: String,
+    /// The station the resource is located at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub station: Option<String>,
+    /// The member who made the booking.
+    pub booker: Member,
+    /// The member the booking was made for.
+    pub owner: Member,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Url>,
+    /// Whether the booking is active, inactive, or cancelled. Never `All`.
+    #[serde(default)]
+    pub state: BookingState,
+}
+
+/// A booking to be created.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewBooking {
+    /// The id of the resource to book.
+    pub resource: ResourceId,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Changes to an existing booking.
+///
+/// Fields which are `None` are kept as they are. A description of `Some(None)`, or `null` in
+/// JSON, removes the description.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BookingChanges {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "present"
+    )]
+    pub description: Option<Option<String>>,
+}
+
+/// Deserialize a value which is present, even if it is `null`, as `Some`.
+fn present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn booking(start: &str, end: &str) -> Booking {
+        Booking {
+            id: "1".into(),
+            resource: "Golf".into(),
+            owner: Member {
+                id: "demo".into(),
+                name: None,
+            },
+            booker: None,
+            start: utc(start),
+            end: utc(end),
+            description: None,
+            location: None,
+            duration: None,
+            flags: vec![],
+            state: BookingState::Active,
+        }
+    }
+
+    fn interval(start: &str, end: &str) -> Interval {
+        Interval {
+            start: utc(start),
+            end: utc(end),
+        }
+    }
+
+    fn range() -> Range<DateTime<Utc>> {
+        utc("2020-03-01T08:00:00Z")..utc("2020-03-01T18:00:00Z")
+    }
+
+    #[test]
+    fn test_availability_empty() {
+        let availability = Availability::from_bookings(&range(), &Vec::new());
+
+        assert!(availability.is_free());
+        assert_eq!(
+            availability.free,
+            vec![interval("2020-03-01T08:00:00Z", "2020-03-01T18:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn test_availability_overlapping() {
+        let bookings = vec![
+            booking("2020-03-01T12:00:00Z", "2020-03-01T14:00:00Z"),
+            booking("2020-03-01T10:00:00Z", "2020-03-01T13:00:00Z"),
+        ];
+        let availability = Availability::from_bookings(&range(), &bookings);
+
+        assert_eq!(
+            availability.busy,
+            vec![interval("2020-03-01T10:00:00Z", "2020-03-01T14:00:00Z")]
+        );
+        assert_eq!(
+            availability.free,
+            vec![
+                interval("2020-03-01T08:00:00Z", "2020-03-01T10:00:00Z"),
+                interval("2020-03-01T14:00:00Z", "2020-03-01T18:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_availability_touching() {
+        let bookings = vec![
+            booking("2020-03-01T10:00:00Z", "2020-03-01T12:00:00Z"),
+            booking("2020-03-01T12:00:00Z", "2020-03-01T14:00:00Z"),
+        ];
+        let availability = Availability::from_bookings(&range(), &bookings);
+
+        // merged, without an empty gap in between
+        assert_eq!(
+            availability.busy,
+            vec![interval("2020-03-01T10:00:00Z", "2020-03-01T14:00:00Z")]
+        );
+        assert_eq!(availability.free.len(), 2);
+    }
+
+    #[test]
+    fn test_availability_outside() {
+        let bookings = vec![
+            // ends before, and starts after the range
+            booking("2020-03-01T06:00:00Z", "2020-03-01T08:00:00Z"),
+            booking("2020-03-01T18:00:00Z", "2020-03-01T20:00:00Z"),
+            // reaches past both ends of the range
+            booking("2020-02-29T06:00:00Z", "2020-03-01T09:00:00Z"),
+            booking("2020-03-01T17:00:00Z", "2020-03-02T09:00:00Z"),
+        ];
+        let availability = Availability::from_bookings(&range(), &bookings);
+
+        assert_eq!(
+            availability.busy,
+            vec![
+                interval("2020-03-01T08:00:00Z", "2020-03-01T09:00:00Z"),
+                interval("2020-03-01T17:00:00Z", "2020-03-01T18:00:00Z"),
+            ]
+        );
+        assert_eq!(
+            availability.free,
+            vec![interval("2020-03-01T09:00:00Z", "2020-03-01T17:00:00Z")]
+        );
+    }
 }
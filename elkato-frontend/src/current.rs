@@ -1,31 +1,29 @@
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, Result};
 
+use elkato_client::transport::FetchTransport;
+use elkato_client::{Error, ProxyClient};
 use elkato_common::data::Booking;
 use patternfly_yew::*;
 use yew::prelude::*;
-use yew::services::fetch::{Request, *};
-
-use headers::authorization::Credentials;
-use headers::Authorization;
 
 use chrono::{Date, DateTime, Duration, TimeZone, Utc};
 use chrono_tz::Europe::Berlin;
 use url::Url;
-use yew::format::{Json, Nothing};
 
 use crate::data::Config;
 use crate::BASE_URL;
 
 pub struct CurrentView {
     link: ComponentLink<Self>,
-    ft: Option<FetchTask>,
     bookings: Vec<Booking>,
+    /// The number of the latest request, responses to earlier requests are ignored.
+    request: usize,
 }
 
 pub enum Msg {
     FetchData,
-    FetchReady(Result<Vec<Booking>, Error>),
-    FetchFailed(String),
+    FetchReady(usize, Vec<Booking>),
+    FetchFailed(usize, String),
     Open(Option<Url>),
 }
 
@@ -38,26 +36,27 @@ impl Component for CurrentView {
 
         Self {
             link,
-            ft: None,
             bookings: vec![],
+            request: 0,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::FetchData => {
-                self.ft = match self.fetch_update() {
-                    Ok(ft) => Some(ft),
-                    Err(err) => {
-                        Self::error(err.to_string());
-                        None
-                    }
+                if let Err(err) = self.fetch_update() {
+                    Self::error(err.to_string());
                 }
             }
-            Msg::FetchReady(response) => {
-                self.bookings = self.select(response.unwrap_or_default());
+            Msg::FetchReady(request, _) | Msg::FetchFailed(request, _)
+                if request != self.request =>
+            {
+                return false;
+            }
+            Msg::FetchReady(_, bookings) => {
+                self.bookings = self.select(bookings);
             }
-            Msg::FetchFailed(msg) => {
+            Msg::FetchFailed(_, msg) => {
                 Self::error(msg);
             }
             Msg::Open(url) => {
@@ -139,7 +138,7 @@ impl CurrentView {
         })
     }
 
-    fn fetch_update(&mut self) -> Result<yew::services::fetch::FetchTask> {
+    fn fetch_update(&mut self) -> Result<()> {
         let config =
             Config::load().map_err(|err| anyhow!("Failed to restore user information: {}", err))?;
 
@@ -150,43 +149,24 @@ impl CurrentView {
             return Err(anyhow!("Missing user information"));
         }
 
-        let callback = self.link.callback(
-            move |response: Response<Json<Result<Vec<Booking>, Error>>>| {
-                let (meta, Json(data)) = response.into_parts();
-                if meta.status.is_success() {
-                    Msg::FetchReady(data)
-                } else {
-                    Msg::FetchFailed(format!("Code: {}", meta.status))
-                }
-            },
-        );
-
-        let auth = Authorization::basic(
-            &config.user.username,
-            config
-                .user
-                .password
-                .as_ref()
-                .map(|s| s.as_str())
-                .unwrap_or(""),
-        );
-
-        let club = &config.user.club;
-        let club = percent_encoding::utf8_percent_encode(club, percent_encoding::NON_ALPHANUMERIC);
-
-        let request = Request::get(format!("{}/{}/bookings/current", BASE_URL.to_owned(), club))
-            .header("Authorization", auth.0.encode())
-            .body(Nothing);
+        let client = ProxyClient::with_transport(BASE_URL.parse()?, FetchTransport);
+        let link = self.link.clone();
 
-        log::debug!("Request: {:?}", request);
+        self.request += 1;
+        let request = self.request;
 
-        let request = request.unwrap();
-
-        let ft = FetchService::fetch(request, callback);
-
-        log::debug!("FT: {:?}", ft);
+        wasm_bindgen_futures::spawn_local(async move {
+            let msg = match client.list_current_bookings(config.user).await {
+                Ok(bookings) => Msg::FetchReady(request, bookings),
+                Err(Error::AuthenticationFailed) => {
+                    Msg::FetchFailed(request, "Invalid username or password".into())
+                }
+                Err(err) => Msg::FetchFailed(request, err.to_string()),
+            };
+            link.send_message(msg);
+        });
 
-        ft
+        Ok(())
     }
 
     fn title(&self, booking: &Booking, now: &DateTime<Utc>) -> String {
@@ -1,6 +1,9 @@
-use actix_web::{get, middleware, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    delete, get, middleware, post, put, web, App, HttpResponse, HttpServer, Responder,
+};
 
-use elkato_client::{Client, Config, ListOptions, User};
+use elkato_client::{Client, Config, Error, ErrorInfo, ListOptions, User};
+use elkato_common::data::{BookingChanges, NewBooking};
 use serde_json::json;
 
 use actix_cors::Cors;
@@ -8,7 +11,23 @@ use actix_web_httpauth::extractors::basic;
 use actix_web_httpauth::extractors::basic::BasicAuth;
 use chrono::{Duration, Local, Utc};
 use futures::stream::TryStreamExt;
-use futures::StreamExt;
+
+/// Map a client error to an HTTP response.
+fn error_response(err: Error) -> HttpResponse {
+    let mut resp = match &err {
+        Error::AuthenticationFailed => HttpResponse::Unauthorized(),
+        Error::InvalidOptions(_) => HttpResponse::BadRequest(),
+        Error::NotFound(_) => HttpResponse::NotFound(),
+        Error::Rejected(_) => HttpResponse::Conflict(),
+        Error::Transport(_) | Error::Maintenance => HttpResponse::ServiceUnavailable(),
+        Error::Upstream(_)
+        | Error::UpstreamHttp(_)
+        | Error::Parse { .. }
+        | Error::MissingField { .. } => HttpResponse::BadGateway(),
+    };
+
+    resp.json(ErrorInfo::from(&err))
+}
 
 #[get("/")]
 async fn index() -> impl Responder {
@@ -20,17 +39,22 @@ async fn health() -> impl Responder {
     HttpResponse::Ok().finish()
 }
 
+/// The Elkato user, from the club and the credentials of the request.
+fn user(club: String, auth: &BasicAuth) -> User {
+    User {
+        club,
+        username: auth.user_id().to_string(),
+        password: auth.password().map(|s| s.to_string()),
+    }
+}
+
 #[get("/{club}/bookings/current")]
 async fn list_current_bookings(
     club: web::Path<String>,
     client: web::Data<Client>,
     auth: BasicAuth,
 ) -> Result<impl Responder, actix_web::Error> {
-    let user = User {
-        club: club.0,
-        username: auth.user_id().to_string(),
-        password: auth.password().map(|s| s.to_string()),
-    };
+    let user = user(club.into_inner(), &auth);
 
     log::info!("Get current");
 
@@ -42,16 +66,109 @@ async fn list_current_bookings(
         .list_bookings(
             user,
             ListOptions {
-                owner: Some(auth.user_id().to_string()),
+                owner: Some(auth.user_id().as_ref().into()),
                 start_from: Some(now.date() - Duration::days(7)),
                 end_to: Some(now.date() + Duration::days(7)),
                 ..Default::default()
             },
         )
-        .boxed()
         .try_collect()
         .await
-        .map_err(|e| HttpResponse::InternalServerError().json(json!({"message": e.to_string()})))?;
+        .map_err(error_response)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/{club}/bookings")]
+async fn list_bookings(
+    club: web::Path<String>,
+    options: web::Query<ListOptions>,
+    client: web::Data<Client>,
+    auth: BasicAuth,
+) -> Result<impl Responder, actix_web::Error> {
+    let result: Vec<_> = client
+        .list_bookings(user(club.into_inner(), &auth), options.into_inner())
+        .try_collect()
+        .await
+        .map_err(error_response)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/{club}/bookings")]
+async fn create_booking(
+    club: web::Path<String>,
+    booking: web::Json<NewBooking>,
+    client: web::Data<Client>,
+    auth: BasicAuth,
+) -> Result<impl Responder, actix_web::Error> {
+    let result = client
+        .create_booking(user(club.into_inner(), &auth), booking.into_inner())
+        .await
+        .map_err(error_response)?;
+
+    Ok(HttpResponse::Created().json(result))
+}
+
+#[get("/{club}/bookings/{id}")]
+async fn get_booking(
+    path: web::Path<(String, String)>,
+    client: web::Data<Client>,
+    auth: BasicAuth,
+) -> Result<impl Responder, actix_web::Error> {
+    let (club, id) = path.into_inner();
+
+    let result = client
+        .get_booking(user(club, &auth), &id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/{club}/bookings/{id}")]
+async fn update_booking(
+    path: web::Path<(String, String)>,
+    changes: web::Json<BookingChanges>,
+    client: web::Data<Client>,
+    auth: BasicAuth,
+) -> Result<impl Responder, actix_web::Error> {
+    let (club, id) = path.into_inner();
+
+    client
+        .update_booking(user(club, &auth), &id, changes.into_inner())
+        .await
+        .map_err(error_response)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/{club}/bookings/{id}")]
+async fn cancel_booking(
+    path: web::Path<(String, String)>,
+    client: web::Data<Client>,
+    auth: BasicAuth,
+) -> Result<impl Responder, actix_web::Error> {
+    let (club, id) = path.into_inner();
+
+    client
+        .cancel_booking(user(club, &auth), &id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/{club}/resources")]
+async fn list_resources(
+    club: web::Path<String>,
+    client: web::Data<Client>,
+    auth: BasicAuth,
+) -> Result<impl Responder, actix_web::Error> {
+    let result = client
+        .list_resources(user(club.into_inner(), &auth))
+        .await
+        .map_err(error_response)?;
 
     Ok(HttpResponse::Ok().json(result))
 }
@@ -60,9 +177,7 @@ async fn list_current_bookings(
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
 
-    let client = elkato_client::Client::new(Config {
-        url: "https://www.elkato.de".parse()?,
-    })?;
+    let client = elkato_client::Client::new(Config::new("https://www.elkato.de".parse()?))?;
 
     let addr = std::env::var("BIND_ADDR").ok();
     let addr = addr.as_ref().map(|s| s.as_str());
@@ -77,6 +192,12 @@ async fn main() -> anyhow::Result<()> {
             .service(index)
             .service(health)
             .service(list_current_bookings)
+            .service(list_bookings)
+            .service(create_booking)
+            .service(get_booking)
+            .service(update_booking)
+            .service(cancel_booking)
+            .service(list_resources)
     })
     .bind(addr.unwrap_or("127.0.0.1:8080"))?
     .run()